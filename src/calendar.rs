@@ -0,0 +1,243 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    io::{self, stdout, IsTerminal, Write},
+};
+
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use serde::Serialize;
+
+use crate::{
+    by_day, output::Report, period::days_into_week, range::DateRange, schedule::HabitDays, Fm,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Day {
+    Done,
+    Missed,
+    NoEntry,
+    // Done on a day the habit is not due.
+    Bonus,
+    // Days with an entry for a habit to avoid, without and with it.
+    Clean,
+    Slip,
+    Skipped,
+    Unscheduled,
+}
+
+impl Day {
+    const fn glyph(self) -> char {
+        match self {
+            Self::Done => '█',
+            Self::Missed => '░',
+            Self::NoEntry => '·',
+            Self::Bonus => '▓',
+            Self::Clean => '█',
+            Self::Slip => '×',
+            Self::Skipped => '-',
+            Self::Unscheduled => ' ',
+        }
+    }
+
+    const fn colour(self) -> &'static str {
+        match self {
+            Self::Done => "\x1b[32m",
+            Self::Missed => "\x1b[90m",
+            Self::NoEntry => "\x1b[2m",
+            Self::Bonus => "\x1b[36m",
+            Self::Clean => "\x1b[32m",
+            Self::Slip => "\x1b[31m",
+            Self::Skipped => "\x1b[34m",
+            Self::Unscheduled => "",
+        }
+    }
+
+    fn render(self, colour: bool) -> String {
+        if colour {
+            format!("{}{}\x1b[0m", self.colour(), self.glyph())
+        } else {
+            self.glyph().to_string()
+        }
+    }
+}
+
+// Colour only when writing to a terminal and NO_COLOR is unset.
+pub(crate) fn use_colour(no_colour: bool) -> bool {
+    !no_colour && env::var_os("NO_COLOR").is_none() && stdout().is_terminal()
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub(crate) struct CalendarDay {
+    date: NaiveDate,
+    status: Day,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct CalendarReport {
+    habit: String,
+    start: NaiveDate,
+    end: NaiveDate,
+    done: usize,
+    scheduled: usize,
+    bonus: usize,
+    skipped: usize,
+    days: Vec<CalendarDay>,
+    #[serde(skip)]
+    colour: bool,
+    #[serde(skip)]
+    week_start: Weekday,
+    #[serde(skip)]
+    avoid: bool,
+}
+
+pub(crate) fn calendar(
+    fm: &[Fm],
+    habit: &str,
+    due: &HabitDays,
+    start: NaiveDate,
+    end: NaiveDate,
+    colour: bool,
+    week_start: Weekday,
+) -> CalendarReport {
+    let entries: BTreeMap<NaiveDate, bool> = by_day(
+        fm.iter()
+            .filter(|f| DateRange::new(start, end).contains(f.date)),
+    )
+    .into_iter()
+    .map(|(date, habits)| (date, habits.get(habit).is_some_and(|v| v.done())))
+    .collect();
+    let days: Vec<CalendarDay> = start
+        .iter_days()
+        .take_while(|date| *date <= end)
+        .map(|date| CalendarDay {
+            date,
+            status: match (due.due(date), entries.get(&date)) {
+                (_, Some(true)) if due.avoid() => Day::Slip,
+                (true, Some(false)) if due.avoid() => Day::Clean,
+                (true, Some(true)) => Day::Done,
+                (true, Some(false)) => Day::Missed,
+                (true, None) => Day::NoEntry,
+                (false, Some(true)) => Day::Bonus,
+                (false, _) if due.skipped(date) => Day::Skipped,
+                (false, _) => Day::Unscheduled,
+            },
+        })
+        .collect();
+    CalendarReport {
+        habit: habit.to_owned(),
+        start,
+        end,
+        done: days
+            .iter()
+            .filter(|day| matches!(day.status, Day::Done | Day::Clean))
+            .count(),
+        scheduled: days.iter().filter(|day| due.due(day.date)).count(),
+        bonus: days.iter().filter(|day| day.status == Day::Bonus).count(),
+        skipped: days.iter().filter(|day| due.skipped(day.date)).count(),
+        days,
+        colour,
+        week_start,
+        avoid: due.avoid(),
+    }
+}
+
+impl Report for CalendarReport {
+    type Row = CalendarDay;
+
+    fn rows(&self) -> Vec<CalendarDay> {
+        self.days.clone()
+    }
+
+    // A GitHub-style grid with a column per week and a row per weekday.
+    fn table(&self, out: &mut dyn Write) -> io::Result<()> {
+        let (start, end) = (self.start, self.end);
+        let first = start - Duration::days(i64::from(days_into_week(start, self.week_start)));
+        let weeks: Vec<NaiveDate> = (0..)
+            .map(|week| first + Duration::weeks(week))
+            .take_while(|week| *week <= end)
+            .collect();
+
+        // Month names go above the week holding the 1st, unless the previous
+        // label still occupies that column.
+        let mut header = String::from("    ");
+        for (n, week) in weeks.iter().enumerate() {
+            let column = 4 + n * 2;
+            if header.len() > column {
+                continue;
+            }
+            header.push_str(&" ".repeat(column - header.len()));
+            let first_of_month = (0..7)
+                .map(|d| *week + Duration::days(d))
+                .find(|d| d.day() == 1 && start <= *d && *d <= end);
+            if let Some(date) = first_of_month.or((n == 0).then_some(start)) {
+                header.push_str(&date.format("%b").to_string());
+            }
+        }
+        writeln!(out, "{}", header.trim_end())?;
+
+        let weekdays = (0..7).scan(self.week_start, |day, _| {
+            let weekday = *day;
+            *day = day.succ();
+            Some(weekday)
+        });
+        for (row, weekday) in (0..).zip(weekdays) {
+            let label = match weekday {
+                Weekday::Mon | Weekday::Wed | Weekday::Fri => weekday.to_string(),
+                _ => String::new(),
+            };
+            let mut line = format!("{label:<4}");
+            for week in &weeks {
+                let date = *week + Duration::days(row);
+                match usize::try_from((date - start).num_days())
+                    .ok()
+                    .and_then(|n| self.days.get(n))
+                {
+                    Some(day) => {
+                        line.push_str(&day.status.render(self.colour));
+                        line.push(' ');
+                    }
+                    None => line.push_str("  "),
+                }
+            }
+            writeln!(out, "{}", line.trim_end())?;
+        }
+
+        let mut bonus = match self.bonus {
+            0 => String::new(),
+            n => format!(", {n} bonus"),
+        };
+        if self.avoid {
+            bonus.insert_str(0, " clean");
+        }
+        if self.skipped > 0 {
+            bonus.push_str(&format!(", {} skipped", self.skipped));
+        }
+        let mut legend = if self.avoid {
+            format!(
+                "{} clean  {} slip",
+                Day::Clean.render(self.colour),
+                Day::Slip.render(self.colour)
+            )
+        } else {
+            format!(
+                "{} done  {} not done",
+                Day::Done.render(self.colour),
+                Day::Missed.render(self.colour)
+            )
+        };
+        legend.push_str(&format!("  {} no entry", Day::NoEntry.render(self.colour)));
+        if self.scheduled < self.days.len() && !self.avoid {
+            legend.push_str(&format!("  {} bonus", Day::Bonus.render(self.colour)));
+        }
+        if self.skipped > 0 {
+            legend.push_str(&format!("  {} skipped", Day::Skipped.render(self.colour)));
+        }
+        writeln!(
+            out,
+            "\n{legend}    {}: {} of {} days{bonus}",
+            self.habit, self.done, self.scheduled
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,67 @@
+use std::{
+    collections::BTreeSet,
+    io::{self, BufRead, Write},
+};
+
+use chrono::NaiveDate;
+
+use crate::{
+    config::{Config, Journal, Settings},
+    edit::{self, Recorded},
+    streak,
+    value::HabitValue,
+    Fm, RibbitR,
+};
+
+// Asks about each habit in turn, showing the streak going into `date`, then
+// records every answer in a single write. Quitting part way writes nothing.
+// Questions go to stderr, leaving stdout to the report of what was written.
+pub(crate) fn checkin(
+    fm: &[Fm],
+    settings: &Settings,
+    config: &Config,
+    journal: &Journal,
+    names: &BTreeSet<String>,
+    date: NaiveDate,
+) -> RibbitR<Recorded> {
+    let before = &fm[..fm.partition_point(|f| f.date < date)];
+    let entry = fm.iter().find(|f| f.date == date);
+    let width = names.iter().map(String::len).max().unwrap_or(0);
+    eprintln!(
+        "{date} - y/n, skip or an amount for each habit, enter keeps the recorded answer, q quits"
+    );
+
+    let mut answers = Vec::new();
+    let mut lines = io::stdin().lock().lines();
+    for habit in names {
+        let due = config.days(habit);
+        let streak = streak::streak(before, habit, &due, settings.missing_days.value, date);
+        let streak = streak.current;
+        let recorded = entry.and_then(|f| f.habits.get(habit)).copied();
+        let choices = match recorded {
+            Some(HabitValue::Bool(true)) => "Y/n".to_owned(),
+            Some(HabitValue::Bool(false)) => "y/N".to_owned(),
+            Some(value) => format!("y/n, {value}"),
+            None => "y/n".to_owned(),
+        };
+        let value = loop {
+            eprint!("{habit:width$}  {streak:>3} day streak  [{choices}] ");
+            io::stderr().flush()?;
+            let Some(line) = lines.next().transpose()? else {
+                eprintln!();
+                return Ok(Recorded::nothing(date));
+            };
+            match (line.trim(), recorded) {
+                ("", Some(value)) => break value,
+                ("", None) => {}
+                ("q" | "quit", _) => return Ok(Recorded::nothing(date)),
+                (answer, _) => match HabitValue::parse(answer) {
+                    Ok(value) => break value,
+                    Err(e) => eprintln!("{e}"),
+                },
+            }
+        };
+        answers.push((config.keys(habit), value));
+    }
+    edit::record(fm, settings, config, journal, date, &answers)
+}
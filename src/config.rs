@@ -0,0 +1,561 @@
+use std::{
+    collections::BTreeMap,
+    env, fmt,
+    fs::read_to_string,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::Weekday;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    goal::Goal,
+    output::{OutputFormat, Report},
+    schedule::{HabitDays, Schedule, DAILY},
+    skip::{SkipRange, Skips},
+    streak::MissingDays,
+    value::Polarity,
+    RibbitR,
+};
+
+pub(crate) const JOURNAL_ENV: &str = "RIBBIT_JOURNAL";
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub(crate) struct Config {
+    // A directory, or the name of one of `journals`.
+    pub(crate) journal: Option<String>,
+    pub(crate) journals: BTreeMap<String, PathBuf>,
+    pub(crate) habits: BTreeMap<String, HabitDef>,
+    pub(crate) week_start: Option<Weekday>,
+    pub(crate) output: Option<OutputFormat>,
+    pub(crate) missing_days: Option<MissingDays>,
+    // strftime pattern for new entries, relative to the journal directory.
+    pub(crate) filename: Option<String>,
+    // Template for new entries, relative to the config file.
+    pub(crate) template: Option<PathBuf>,
+    // Date ranges, such as holidays, that count for nothing.
+    pub(crate) skip: Vec<SkipRange>,
+    // The file this was read from, if any.
+    #[serde(skip)]
+    pub(crate) path: Option<PathBuf>,
+    // Skip days from the config and the journal, gathered once it is read.
+    #[serde(skip)]
+    pub(crate) skips: Skips,
+}
+
+#[derive(Deserialize, Default, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct HabitDef {
+    pub(crate) aliases: Vec<String>,
+    // What amounts are counted in, e.g. `pages`.
+    pub(crate) unit: Option<String>,
+    // e.g. `4x per week`, `20 pages per day` or `weekdays`.
+    pub(crate) goal: Option<Goal>,
+    // e.g. `weekdays`, `mon,wed,fri` or `every 2 days`.
+    pub(crate) schedule: Schedule,
+    // `avoid` for habits like smoking, where doing it is a slip.
+    pub(crate) polarity: Polarity,
+}
+
+impl Config {
+    // Reads `--config` if given, otherwise the XDG config file if it exists.
+    pub(crate) fn load(explicit: Option<&Path>) -> RibbitR<Self> {
+        let path = match explicit {
+            Some(path) => path.to_path_buf(),
+            None => match default_path() {
+                Some(path) if path.exists() => path,
+                _ => return Ok(Self::default()),
+            },
+        };
+        let contents = read_to_string(&path)
+            .map_err(|e| format!("cannot read config {}: {e}", path.display()))?;
+        let mut config: Self = toml::from_str(&contents)
+            .map_err(|e| format!("invalid config {}: {e}", path.display()))?;
+        config.path = Some(path);
+        Ok(config)
+    }
+
+    // Maps every declared alias to its canonical habit name.
+    pub(crate) fn aliases(&self) -> BTreeMap<&str, &str> {
+        self.habits
+            .iter()
+            .flat_map(|(name, def)| def.aliases.iter().map(move |a| (a.as_str(), name.as_str())))
+            .collect()
+    }
+
+    // A declared journal by name, or else a journal directory named after its
+    // last component.
+    fn journal(&self, journal: &str) -> Journal {
+        match self.journals.get(journal) {
+            Some(path) => Journal::new(journal, path.clone()),
+            None => Journal::at(PathBuf::from(journal)),
+        }
+    }
+
+    // `--journal` values, where `all` stands for every declared journal.
+    fn named_journals(&self, names: &[String]) -> RibbitR<Vec<Journal>> {
+        let mut journals: Vec<Journal> = Vec::new();
+        for name in names {
+            if name == "all" {
+                journals.extend(
+                    self.journals
+                        .iter()
+                        .map(|(n, p)| Journal::new(n, p.clone())),
+                );
+            } else if let Some(path) = self.journals.get(name) {
+                journals.push(Journal::new(name, path.clone()));
+            } else {
+                let declared: Vec<&str> = self.journals.keys().map(String::as_str).collect();
+                return Err(format!(
+                    "unknown journal `{name}`, declared journals: {}",
+                    declared.join(", ")
+                )
+                .into());
+            }
+        }
+        let mut seen = Vec::new();
+        journals.retain(|j| {
+            let new = !seen.contains(&j.name);
+            seen.push(j.name.clone());
+            new
+        });
+        if journals.is_empty() {
+            return Err("no journals declared in the config".into());
+        }
+        Ok(journals)
+    }
+
+    pub(crate) fn canonical<'a>(&'a self, habit: &'a str) -> &'a str {
+        self.aliases().get(habit).copied().unwrap_or(habit)
+    }
+
+    pub(crate) fn days<'a>(&'a self, habit: &'a str) -> HabitDays<'a> {
+        let def = self.habits.get(habit);
+        HabitDays {
+            habit,
+            schedule: def.map_or(&DAILY, |def| &def.schedule),
+            skips: &self.skips,
+            polarity: def.map(|def| def.polarity).unwrap_or_default(),
+        }
+    }
+
+    pub(crate) fn unit(&self, habit: &str) -> Option<&str> {
+        self.habits.get(habit)?.unit.as_deref()
+    }
+
+    // Every name a habit may be written under, canonical name first.
+    pub(crate) fn keys<'a>(&'a self, habit: &'a str) -> Vec<&'a str> {
+        let aliases = self.habits.get(habit).map(|def| def.aliases.as_slice());
+        std::iter::once(habit)
+            .chain(aliases.unwrap_or_default().iter().map(String::as_str))
+            .collect()
+    }
+
+    fn file_source(&self) -> Source {
+        Source::File(self.path.clone().unwrap_or_default())
+    }
+
+    fn setting<T>(&self, value: Option<T>, default: T) -> Setting<T> {
+        match value {
+            Some(value) => Setting::new(value, self.file_source()),
+            None => Setting::new(default, Source::Default),
+        }
+    }
+}
+
+pub(crate) fn default_path() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("ribbit").join("config.toml"))
+}
+
+// `--config` has to be known before the command line is parsed, since the
+// config decides which habit names the parser accepts.
+pub(crate) fn config_arg() -> Option<PathBuf> {
+    let mut args = env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg.to_str().and_then(|a| a.strip_prefix("--config=")) {
+            return Some(PathBuf::from(path));
+        }
+    }
+    None
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Journal {
+    pub(crate) name: String,
+    pub(crate) path: PathBuf,
+}
+
+impl Journal {
+    fn new(name: &str, path: PathBuf) -> Self {
+        Self {
+            name: name.to_owned(),
+            path,
+        }
+    }
+
+    fn at(path: PathBuf) -> Self {
+        let name = path.file_name().unwrap_or(path.as_os_str());
+        Self::new(&name.to_string_lossy(), path.clone())
+    }
+}
+
+impl fmt::Display for Journal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.name, self.path.display())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Source {
+    Default,
+    File(PathBuf),
+    Env(&'static str),
+    Flag(&'static str),
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "default"),
+            Self::File(path) => write!(f, "{}", path.display()),
+            Self::Env(var) => write!(f, "${var}"),
+            Self::Flag(flag) => write!(f, "{flag}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Setting<T> {
+    pub(crate) value: T,
+    pub(crate) source: Source,
+}
+
+impl<T> Setting<T> {
+    const fn new(value: T, source: Source) -> Self {
+        Self { value, source }
+    }
+}
+
+// The configuration in effect once the config file, environment and command
+// line have been layered, in that order of precedence from lowest.
+#[derive(Debug)]
+pub(crate) struct Settings {
+    pub(crate) journals: Option<Setting<Vec<Journal>>>,
+    pub(crate) week_start: Setting<Weekday>,
+    pub(crate) output: Setting<OutputFormat>,
+    pub(crate) missing_days: Setting<MissingDays>,
+    pub(crate) filename: Setting<String>,
+    pub(crate) template: Option<Setting<PathBuf>>,
+}
+
+impl Settings {
+    pub(crate) fn new(
+        config: &Config,
+        journal_arg: Option<PathBuf>,
+        journal_env: Option<String>,
+        journal_names: &[String],
+        output_arg: Option<OutputFormat>,
+    ) -> RibbitR<Self> {
+        if journal_arg.is_some() && !journal_names.is_empty() {
+            return Err("give either a journal directory or --journal, not both".into());
+        }
+        let journals = if journal_names.is_empty() {
+            journal_arg
+                .map(|dir| Setting::new(vec![Journal::at(dir)], Source::Flag("JOURNAL_DIR")))
+                .or_else(|| {
+                    journal_env
+                        .map(|j| Setting::new(vec![config.journal(&j)], Source::Env(JOURNAL_ENV)))
+                })
+                .or_else(|| {
+                    config
+                        .journal
+                        .as_ref()
+                        .map(|j| Setting::new(vec![config.journal(j)], config.file_source()))
+                })
+        } else {
+            Some(Setting::new(
+                config.named_journals(journal_names)?,
+                Source::Flag("--journal"),
+            ))
+        };
+        let output = match output_arg {
+            Some(output) => Setting::new(output, Source::Flag("--output")),
+            None => config.setting(config.output, OutputFormat::default()),
+        };
+        Ok(Self {
+            journals,
+            week_start: config.setting(config.week_start, Weekday::Mon),
+            output,
+            missing_days: config.setting(config.missing_days, MissingDays::default()),
+            filename: config.setting(config.filename.clone(), "%Y-%m-%d.md".to_owned()),
+            template: config.template.as_ref().map(|template| {
+                let dir = config.path.as_deref().and_then(Path::parent);
+                let template = dir.map_or_else(|| template.clone(), |dir| dir.join(template));
+                Setting::new(template, config.file_source())
+            }),
+        })
+    }
+
+    // Commands that write entries need exactly one journal to write to.
+    pub(crate) fn journal(&self) -> RibbitR<&Journal> {
+        match self.journals()? {
+            [journal] => Ok(journal),
+            _ => Err("this writes to a single journal, pick one with --journal".into()),
+        }
+    }
+
+    pub(crate) fn journals(&self) -> RibbitR<&[Journal]> {
+        self.journals
+            .as_ref()
+            .map(|s| s.value.as_slice())
+            .ok_or_else(|| {
+                format!(
+                    "no journal directory: pass one, set ${JOURNAL_ENV}, or set `journal` in {}",
+                    default_path()
+                        .map_or_else(|| "the config file".to_owned(), |p| p.display().to_string())
+                )
+                .into()
+            })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ConfigRow {
+    key: &'static str,
+    value: String,
+    source: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(transparent)]
+pub(crate) struct ConfigReport(Vec<ConfigRow>);
+
+impl ConfigReport {
+    pub(crate) fn new(settings: &Settings, config: &Config) -> Self {
+        fn row<T: fmt::Debug>(key: &'static str, setting: &Setting<T>) -> ConfigRow {
+            ConfigRow {
+                key,
+                value: format!("{:?}", setting.value).to_lowercase(),
+                source: setting.source.to_string(),
+            }
+        }
+        let journal = settings.journals.as_ref().map_or_else(
+            || ConfigRow {
+                key: "journal",
+                value: String::new(),
+                source: "unset".to_owned(),
+            },
+            |journals| ConfigRow {
+                key: "journal",
+                value: join(&journals.value),
+                source: journals.source.to_string(),
+            },
+        );
+        let journals: Vec<Journal> = config
+            .journals
+            .iter()
+            .map(|(name, path)| Journal::new(name, path.clone()))
+            .collect();
+        let journals = ConfigRow {
+            key: "journals",
+            value: join(&journals),
+            source: if journals.is_empty() {
+                Source::Default
+            } else {
+                config.file_source()
+            }
+            .to_string(),
+        };
+        let habits = ConfigRow {
+            key: "habits",
+            value: config.habits.keys().cloned().collect::<Vec<_>>().join(", "),
+            source: if config.habits.is_empty() {
+                Source::Default
+            } else {
+                config.file_source()
+            }
+            .to_string(),
+        };
+        let skip = ConfigRow {
+            key: "skip",
+            value: config
+                .skip
+                .iter()
+                .map(SkipRange::describe)
+                .collect::<Vec<_>>()
+                .join(", "),
+            source: if config.skip.is_empty() {
+                Source::Default
+            } else {
+                config.file_source()
+            }
+            .to_string(),
+        };
+        Self(vec![
+            journal,
+            journals,
+            habits,
+            skip,
+            row("week_start", &settings.week_start),
+            row("output", &settings.output),
+            row("missing_days", &settings.missing_days),
+            ConfigRow {
+                key: "filename",
+                value: settings.filename.value.clone(),
+                source: settings.filename.source.to_string(),
+            },
+            settings.template.as_ref().map_or_else(
+                || ConfigRow {
+                    key: "template",
+                    value: String::new(),
+                    source: Source::Default.to_string(),
+                },
+                |template| ConfigRow {
+                    key: "template",
+                    value: template.value.display().to_string(),
+                    source: template.source.to_string(),
+                },
+            ),
+        ])
+    }
+}
+
+impl Report for ConfigReport {
+    type Row = ConfigRow;
+
+    fn rows(&self) -> Vec<ConfigRow> {
+        self.0.clone()
+    }
+
+    fn table(&self, out: &mut dyn Write) -> io::Result<()> {
+        let width = self.0.iter().map(|row| row.key.len()).max().unwrap_or(0);
+        for row in &self.0 {
+            writeln!(out, "{:width$} = {}  ({})", row.key, row.value, row.source)?;
+        }
+        Ok(())
+    }
+}
+
+fn join(journals: &[Journal]) -> String {
+    journals
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(toml: &str) -> Config {
+        let mut config: Config = toml::from_str(toml).unwrap();
+        config.path = Some(PathBuf::from("/etc/ribbit.toml"));
+        config
+    }
+
+    fn journal(settings: &Settings) -> (Vec<PathBuf>, String) {
+        let journals = settings.journals.as_ref().unwrap();
+        let paths = journals.value.iter().map(|j| j.path.clone()).collect();
+        (paths, journals.source.to_string())
+    }
+
+    fn settings(config: &Config, arg: Option<&str>, env: Option<&str>) -> Settings {
+        Settings::new(
+            config,
+            arg.map(PathBuf::from),
+            env.map(str::to_owned),
+            &[],
+            None,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn journal_precedence() {
+        let file = config("journal = \"/file\"");
+        let paths = |dir: &str| vec![PathBuf::from(dir)];
+        assert_eq!(
+            journal(&settings(&file, Some("/flag"), Some("/env"))),
+            (paths("/flag"), "JOURNAL_DIR".to_owned())
+        );
+        assert_eq!(
+            journal(&settings(&file, None, Some("/env"))),
+            (paths("/env"), "$RIBBIT_JOURNAL".to_owned())
+        );
+        assert_eq!(
+            journal(&settings(&file, None, None)),
+            (paths("/file"), "/etc/ribbit.toml".to_owned())
+        );
+        assert!(settings(&Config::default(), None, None).journals.is_none());
+    }
+
+    #[test]
+    fn env_names_a_journal() {
+        let config = config("[journals]\nwork = \"/work\"");
+        let settings = settings(&config, None, Some("work"));
+        assert_eq!(settings.journal().unwrap().name, "work");
+        assert_eq!(settings.journal().unwrap().path, PathBuf::from("/work"));
+    }
+
+    #[test]
+    fn named_journals() {
+        let config = config("[journals]\nhome = \"/home\"\nwork = \"/work\"");
+        let names = ["all".to_owned(), "work".to_owned()];
+        let settings = Settings::new(&config, None, None, &names, None).unwrap();
+        let (paths, source) = journal(&settings);
+        assert_eq!(paths, [PathBuf::from("/home"), PathBuf::from("/work")]);
+        assert_eq!(source, "--journal");
+        assert!(settings.journal().is_err());
+        assert!(Settings::new(&config, None, None, &["play".to_owned()], None).is_err());
+        let dir = Some(PathBuf::from("/flag"));
+        assert!(Settings::new(&config, dir, None, &names, None).is_err());
+    }
+
+    #[test]
+    fn sources() {
+        let file = config("output = \"json\"\ntemplate = \"entry.md\"");
+        let settings = settings(&file, None, None);
+        assert_eq!(settings.output.value, OutputFormat::Json);
+        assert_eq!(
+            settings.output.source,
+            Source::File("/etc/ribbit.toml".into())
+        );
+        assert_eq!(settings.week_start.value, Weekday::Mon);
+        assert_eq!(settings.week_start.source, Source::Default);
+        let template = settings.template.as_ref().unwrap();
+        assert_eq!(template.value, PathBuf::from("/etc/entry.md"));
+
+        let flag = Settings::new(&file, None, None, &[], Some(OutputFormat::Csv)).unwrap();
+        assert_eq!(flag.output.value, OutputFormat::Csv);
+        assert_eq!(flag.output.source, Source::Flag("--output"));
+
+        let rows = ConfigReport::new(&settings, &file).rows();
+        let row = |key| rows.iter().find(|row| row.key == key).unwrap();
+        assert_eq!(
+            (row("output").value.as_str(), row("output").source.as_str()),
+            ("json", "/etc/ribbit.toml")
+        );
+        assert_eq!(row("week_start").source, "default");
+        assert_eq!(row("journal").source, "unset");
+    }
+
+    #[test]
+    fn aliases() {
+        let config = config("[habits.workout]\naliases = [\"gym\", \"exercise\"]\n[habits.read]");
+        assert_eq!(config.canonical("gym"), "workout");
+        assert_eq!(config.canonical("workout"), "workout");
+        assert_eq!(config.canonical("swim"), "swim");
+        assert_eq!(config.keys("workout"), ["workout", "gym", "exercise"]);
+        assert_eq!(config.keys("read"), ["read"]);
+        assert_eq!(config.keys("swim"), ["swim"]);
+    }
+}
@@ -0,0 +1,1022 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    io::{self, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use chrono::{
+    format::{Item, StrftimeItems},
+    NaiveDate,
+};
+use serde::{de::IgnoredAny, Serialize};
+
+use crate::{
+    config::{Config, Journal, Settings},
+    frontmatter::{extract, Format},
+    output::Report,
+    value::HabitValue,
+    Fm, RibbitR,
+};
+
+// The entry a command wrote, if any, and the values it recorded there.
+#[derive(Debug, Serialize)]
+pub(crate) struct Recorded {
+    date: NaiveDate,
+    path: Option<PathBuf>,
+    habits: BTreeMap<String, HabitValue>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct RecordedRow {
+    date: NaiveDate,
+    path: Option<PathBuf>,
+    habit: Option<String>,
+    value: Option<HabitValue>,
+}
+
+impl Recorded {
+    pub(crate) const fn nothing(date: NaiveDate) -> Self {
+        Self {
+            date,
+            path: None,
+            habits: BTreeMap::new(),
+        }
+    }
+
+    pub(crate) const fn created(date: NaiveDate, path: PathBuf) -> Self {
+        Self {
+            date,
+            path: Some(path),
+            habits: BTreeMap::new(),
+        }
+    }
+}
+
+impl Report for Recorded {
+    type Row = RecordedRow;
+
+    fn rows(&self) -> Vec<RecordedRow> {
+        let row = |habit, value| RecordedRow {
+            date: self.date,
+            path: self.path.clone(),
+            habit,
+            value,
+        };
+        if self.habits.is_empty() {
+            return vec![row(None, None)];
+        }
+        self.habits
+            .iter()
+            .map(|(habit, &value)| row(Some(habit.clone()), Some(value)))
+            .collect()
+    }
+
+    fn table(&self, out: &mut dyn Write) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            writeln!(out, "nothing written")?;
+            return Ok(());
+        };
+        match self.habits.first_key_value() {
+            Some((habit, value)) if self.habits.len() == 1 => {
+                let state = match value {
+                    HabitValue::Bool(true) => "done".to_owned(),
+                    HabitValue::Bool(false) => "not done".to_owned(),
+                    value => value.to_string(),
+                };
+                writeln!(
+                    out,
+                    "{habit} {state} on {} in {}",
+                    self.date,
+                    path.display()
+                )?;
+            }
+            _ => writeln!(out, "{}", path.display())?,
+        }
+        Ok(())
+    }
+}
+
+// Records a value for each habit on `date`, editing that day's entry or
+// creating one, and writes the file once. Each habit comes with the names it
+// may be written under, canonical name first.
+pub(crate) fn record(
+    fm: &[Fm],
+    settings: &Settings,
+    config: &Config,
+    journal: &Journal,
+    date: NaiveDate,
+    habits: &[(Vec<&str>, HabitValue)],
+) -> RibbitR<Recorded> {
+    let (path, file) = match existing(fm, date)? {
+        Some(path) => {
+            let file = fs::read_to_string(&path)?;
+            (path, file)
+        }
+        None => new_entry(fm, settings, config, journal, date)?,
+    };
+    let mut edited = file.clone();
+    for (keys, value) in habits {
+        edited =
+            set_habit(&edited, keys, *value).map_err(|e| format!("{}: {e}", path.display()))?;
+    }
+    for (keys, value) in habits {
+        check(&edited, keys, *value).map_err(|e| format!("{}: {e}", path.display()))?;
+    }
+    if body(&edited) != body(&file) {
+        return Err(format!("{}: edit would change the entry's body", path.display()).into());
+    }
+    if edited != file || !path.exists() {
+        write_atomic(&path, &edited)?;
+    }
+    Ok(Recorded {
+        date,
+        path: Some(path),
+        habits: habits
+            .iter()
+            .map(|(keys, value)| (keys[0].to_owned(), *value))
+            .collect(),
+    })
+}
+
+// The path of the day's entry, if there is one.
+fn existing(fm: &[Fm], date: NaiveDate) -> RibbitR<Option<PathBuf>> {
+    let entries: Vec<&Fm> = fm.iter().filter(|f| f.date == date).collect();
+    match entries.as_slice() {
+        [] => Ok(None),
+        [entry] => Ok(Some(entry.path.clone())),
+        entries => {
+            let paths: Vec<String> = entries
+                .iter()
+                .map(|f| f.path.display().to_string())
+                .collect();
+            Err(format!("several entries for {date}: {}", paths.join(", ")).into())
+        }
+    }
+}
+
+const TEMPLATE: &str = "---\ntitle: {title}\ndate: {date}\nhabits:\n{habits}---\n";
+
+// Where the day's entry goes and what it starts out as, checking the filled
+// in template parses.
+pub(crate) fn new_entry(
+    fm: &[Fm],
+    settings: &Settings,
+    config: &Config,
+    journal: &Journal,
+    date: NaiveDate,
+) -> RibbitR<(PathBuf, String)> {
+    if let Some(path) = existing(fm, date)? {
+        return Err(format!("{date} already has an entry: {}", path.display()).into());
+    }
+    let pattern = &settings.filename.value;
+    if StrftimeItems::new(pattern).any(|item| item == Item::Error) {
+        return Err(format!("invalid filename pattern `{pattern}`").into());
+    }
+    let path = journal.path.join(date.format(pattern).to_string());
+    if path.exists() {
+        return Err(format!("{} already exists", path.display()).into());
+    }
+
+    let template = match &settings.template {
+        Some(template) => fs::read_to_string(&template.value)
+            .map_err(|e| format!("cannot read template {}: {e}", template.value.display()))?,
+        None => TEMPLATE.to_owned(),
+    };
+    let file = fill(&template, config, date);
+
+    let entry: Result<Fm, _> = extract(&file)
+        .map_err(str::to_owned)
+        .and_then(|block| block.parse(&path).map_err(|e| e.reason));
+    match entry {
+        Ok(entry) if entry.date == date => Ok((path, file)),
+        Ok(entry) => Err(format!("template gave date {} instead of {date}", entry.date).into()),
+        Err(e) => Err(format!("template does not make a valid entry: {e}").into()),
+    }
+}
+
+// The template with `{title}`, `{date}` and `{habits}` filled in, every
+// configured habit false and written the way the template's format lists
+// them: `  - read: false` lines for YAML, `read = false` lines for TOML, and
+// `"read": false` items for JSON.
+fn fill(template: &str, config: &Config, date: NaiveDate) -> String {
+    let first = template.trim_start_matches('\u{feff}').lines().next();
+    let keys = config.habits.keys();
+    let habits: String = match first.and_then(Format::detect) {
+        Some(Format::Toml) => keys
+            .map(|habit| format!("{} = false\n", toml_key(habit)))
+            .collect(),
+        Some(Format::Json) => keys
+            .map(|habit| format!("{}: false", serde_json::Value::from(habit.as_str())))
+            .collect::<Vec<_>>()
+            .join(", "),
+        Some(Format::Yaml) | None => keys.map(|habit| format!("  - {habit}: false\n")).collect(),
+    };
+    template
+        .replace("{title}", &date.format("%A, %B %-d, %Y").to_string())
+        .replace("{date}", &date.to_string())
+        .replace("{habits}", &habits)
+}
+
+// Opens `path` in $VISUAL or $EDITOR, which may carry its own arguments.
+pub(crate) fn open_editor(path: &Path) -> RibbitR<()> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .map_err(|_| "set $EDITOR to open the new entry")?;
+    let mut words = editor.split_whitespace();
+    let program = words.next().ok_or("$EDITOR is empty")?;
+    let status = Command::new(program).args(words).arg(path).status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("{program} exited with {status}").into())
+    }
+}
+
+// Sets the habit in the front matter of `file`, leaving every other byte of
+// the file as it was.
+pub(crate) fn set_habit(file: &str, keys: &[&str], value: HabitValue) -> Result<String, String> {
+    let block = extract(file)?;
+    let newline = if file[..block.start].ends_with("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let lines: Vec<&str> = block.src.split_inclusive('\n').collect();
+    let src = match block.format {
+        Format::Yaml => set_yaml(lines, keys, value, newline)?.concat(),
+        Format::Toml => set_toml(lines, keys, value, newline)?.concat(),
+        Format::Json => set_json(block.src, keys, value)?,
+    };
+    let end = block.start + block.src.len();
+    Ok([&file[..block.start], &src, &file[end..]].concat())
+}
+
+// A line's key and what follows its separator, e.g. `exercise` and ` true`.
+fn split_key(line: &str, separator: char) -> Option<(&str, &str)> {
+    let (key, value) = line.split_once(separator)?;
+    Some((key.trim().trim_matches(['"', '\'']), value))
+}
+
+// Replaces the value after `separator`, keeping a trailing comment.
+fn with_value(line: &str, separator: char, value: HabitValue, newline: &str) -> String {
+    let (key, old) = line.split_once(separator).unwrap_or((line, ""));
+    let comment = old
+        .find(" #")
+        .map_or("", |n| old[old[..n].trim_end().len()..].trim_end());
+    let value = literal(value, separator);
+    format!("{key}{separator} {value}{comment}{newline}")
+}
+
+// A value as written after `separator`; TOML strings need quotes.
+fn literal(value: HabitValue, separator: char) -> String {
+    if separator == '=' && value.is_text() {
+        format!("\"{value}\"")
+    } else {
+        value.to_string()
+    }
+}
+
+fn set_yaml(
+    lines: Vec<&str>,
+    keys: &[&str],
+    value: HabitValue,
+    newline: &str,
+) -> Result<Vec<String>, String> {
+    let Some(header) = lines.iter().position(|l| l.starts_with("habits:")) else {
+        let mut lines: Vec<String> = lines.into_iter().map(str::to_owned).collect();
+        lines.push(format!("habits:{newline}"));
+        lines.push(format!("  {}: {value}{newline}", keys[0]));
+        return Ok(lines);
+    };
+    let emptied = format!("habits:{newline}");
+    let mut lines: Vec<&str> = lines;
+    let inline = lines[header]["habits:".len()..]
+        .split(" #")
+        .next()
+        .unwrap_or("")
+        .trim();
+    match inline {
+        "" => {}
+        "~" | "null" => lines[header] = &emptied,
+        _ => {
+            let mut lines: Vec<String> = lines.into_iter().map(str::to_owned).collect();
+            lines[header] = set_flow(&lines[header], ':', keys[0], keys, value)?;
+            return Ok(lines);
+        }
+    }
+
+    let children = lines[header + 1..]
+        .iter()
+        .take_while(|l| l.trim().is_empty() || l.starts_with([' ', '\t', '-']))
+        .count();
+    let last = (header + 1..=header + children)
+        .rev()
+        .find(|&n| !lines[n].trim().is_empty())
+        .unwrap_or(header);
+    let first = lines[header + 1..=last].first().copied();
+    let indent = first.map_or("  ", |l| &l[..l.len() - l.trim_start().len()]);
+    let listed = first.is_some_and(|l| l.trim_start().starts_with('-'));
+    let names_only = first.is_some_and(|l| listed && !l.contains(':'));
+
+    let mut out: Vec<String> = Vec::new();
+    let mut found = false;
+    for (n, line) in lines.iter().enumerate() {
+        let item = line.trim_start().strip_prefix('-').map(str::trim_start);
+        let entry = item.unwrap_or_else(|| line.trim_start());
+        let key = split_key(entry, ':').map_or_else(|| entry.trim(), |(key, _)| key);
+        if n > header && n <= last && keys.contains(&key) {
+            found = true;
+            match split_key(entry, ':') {
+                Some(_) => out.push(with_value(line, ':', value, newline)),
+                // A list of names only holds the habits that were done, so an
+                // amount turns the name into a single-key map.
+                None => match value {
+                    HabitValue::Bool(true) => out.push((*line).to_owned()),
+                    HabitValue::Bool(false) => {}
+                    _ => {
+                        let indent = &line[..line.len() - line.trim_start().len()];
+                        out.push(format!("{indent}- {key}: {value}{newline}"));
+                    }
+                },
+            }
+        } else {
+            out.push((*line).to_owned());
+        }
+        if n == last && !found {
+            match (listed, names_only) {
+                (true, true) if value == HabitValue::Bool(true) => {
+                    out.push(format!("{indent}- {}{newline}", keys[0]));
+                }
+                (true, true) if value == HabitValue::Bool(false) => {}
+                (true, _) => out.push(format!("{indent}- {}: {value}{newline}", keys[0])),
+                (false, _) => out.push(format!("{indent}{}: {value}{newline}", keys[0])),
+            }
+        }
+    }
+    Ok(out)
+}
+
+// A TOML key, quoted unless it is bare.
+fn toml_key(key: &str) -> String {
+    if key
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        key.to_owned()
+    } else {
+        format!("{key:?}")
+    }
+}
+
+fn set_toml(
+    lines: Vec<&str>,
+    keys: &[&str],
+    value: HabitValue,
+    newline: &str,
+) -> Result<Vec<String>, String> {
+    let key = toml_key(keys[0]);
+    let Some(header) = lines.iter().position(|l| l.trim() == "[habits]") else {
+        let mut lines: Vec<String> = lines.into_iter().map(str::to_owned).collect();
+        let inline = lines
+            .iter()
+            .position(|l| split_key(l, '=').is_some_and(|(k, _)| k == "habits"));
+        if let Some(n) = inline {
+            lines[n] = set_flow(&lines[n], '=', &key, keys, value)?;
+            return Ok(lines);
+        }
+        lines.push(format!("[habits]{newline}"));
+        lines.push(format!("{key} = {}{newline}", literal(value, '=')));
+        return Ok(lines);
+    };
+    let children = lines[header + 1..]
+        .iter()
+        .take_while(|l| !l.trim_start().starts_with('['))
+        .count();
+    let last = (header + 1..=header + children)
+        .rev()
+        .find(|&n| !lines[n].trim().is_empty())
+        .unwrap_or(header);
+
+    let mut out: Vec<String> = Vec::new();
+    let mut found = false;
+    for (n, line) in lines.iter().enumerate() {
+        let matches = split_key(line, '=').is_some_and(|(k, _)| keys.contains(&k));
+        if n > header && n <= last && matches {
+            found = true;
+            out.push(with_value(line, '=', value, newline));
+        } else {
+            out.push((*line).to_owned());
+        }
+        if n == last && !found {
+            out.push(format!("{key} = {}{newline}", literal(value, '=')));
+        }
+    }
+    Ok(out)
+}
+
+// Edits a one-line `habits` map or list of names such as `{read: true}` or
+// `["read"]`, normalising only the spacing between its items.
+fn set_flow(
+    line: &str,
+    separator: char,
+    key: &str,
+    keys: &[&str],
+    value: HabitValue,
+) -> Result<String, String> {
+    let unreadable = || "`habits` is neither a map nor a list".to_owned();
+    let (name, rest) = line.split_once(separator).ok_or_else(unreadable)?;
+    let end = rest.trim_end().len();
+    let (rest, newline) = rest.split_at(end);
+    let flow = rest.find(" #").map_or(rest, |n| rest[..n].trim_end());
+    let comment = &rest[flow.len()..];
+    let lead = &flow[..flow.len() - flow.trim_start().len()];
+    let flow = flow.trim();
+    let (open, close) = (flow.chars().next(), flow.chars().last());
+    let inner = flow.get(1..flow.len() - 1).ok_or_else(unreadable)?;
+    let pad = if inner.starts_with(' ') { " " } else { "" };
+    let mut items: Vec<String> = inner
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_owned)
+        .collect();
+    match (open, close) {
+        (Some('{'), Some('}')) => {
+            let space = if separator == '=' { " " } else { "" };
+            let mut found = false;
+            for item in &mut items {
+                if let Some((k, _)) = item.split_once(separator) {
+                    if keys.contains(&k.trim().trim_matches(['"', '\''])) {
+                        let value = literal(value, separator);
+                        *item = format!("{}{space}{separator} {value}", k.trim_end());
+                        found = true;
+                    }
+                }
+            }
+            if !found {
+                let value = literal(value, separator);
+                items.push(format!("{key}{space}{separator} {value}"));
+            }
+        }
+        (Some('['), Some(']')) => {
+            let listed = |item: &String| keys.contains(&item.trim_matches(['"', '\'']));
+            match value {
+                HabitValue::Bool(false) => items.retain(|item| !listed(item)),
+                HabitValue::Bool(true) if !items.iter().any(listed) => {
+                    let quote = if separator == '=' { "\"" } else { "" };
+                    items.push(format!("{quote}{}{quote}", keys[0]));
+                }
+                HabitValue::Bool(true) => {}
+                _ => return Err("a list of habit names cannot hold an amount".to_owned()),
+            }
+        }
+        _ => return Err(unreadable()),
+    }
+    Ok(format!(
+        "{name}{separator}{lead}{}{pad}{}{pad}{}{comment}{newline}",
+        &flow[..1],
+        items.join(", "),
+        &flow[flow.len() - 1..]
+    ))
+}
+
+// Edits the `habits` of a JSON object: a map, a list of names and
+// single-key maps, or null. Only the items that change are rewritten.
+fn set_json(src: &str, keys: &[&str], value: HabitValue) -> Result<String, String> {
+    let unreadable = || "`habits` is neither a map nor a list".to_owned();
+    let name = json_string(keys[0]);
+    let literal = json_literal(value);
+    let (items, close) = json_items(src, skip_space(src, 0))?;
+    let mut edits = Vec::new();
+    let Some(habits) = items.iter().find(|i| i.key.as_deref() == Some("habits")) else {
+        let item = format!("\"habits\": {{{name}: {literal}}}");
+        json_push(src, &mut edits, &items, close, item);
+        return Ok(apply(src, 0, edits));
+    };
+    let at = habits.value.start;
+    match &src[at..=at] {
+        "n" => edits.push((habits.value.clone(), format!("{{{name}: {literal}}}"))),
+        "{" => {
+            let (habits, close) = json_items(src, at)?;
+            set_json_map(&habits, keys, &literal, &mut edits);
+            if edits.is_empty() {
+                json_push(
+                    src,
+                    &mut edits,
+                    &habits,
+                    close,
+                    format!("{name}: {literal}"),
+                );
+            }
+        }
+        "[" => set_json_list(src, at, keys, value, &mut edits)?,
+        _ => return Err(unreadable()),
+    }
+    Ok(apply(src, 0, edits))
+}
+
+fn set_json_map(
+    items: &[JsonItem],
+    keys: &[&str],
+    literal: &str,
+    edits: &mut Vec<(Range<usize>, String)>,
+) {
+    for item in items {
+        if item.key.as_deref().is_some_and(|k| keys.contains(&k)) {
+            edits.push((item.value.clone(), literal.to_owned()));
+        }
+    }
+}
+
+// Like a YAML list, a list of names only holds the habits that were done, so
+// an amount turns the name into a single-key map.
+fn set_json_list(
+    src: &str,
+    open: usize,
+    keys: &[&str],
+    value: HabitValue,
+    edits: &mut Vec<(Range<usize>, String)>,
+) -> Result<(), String> {
+    let literal = json_literal(value);
+    let (items, close) = json_items(src, open)?;
+    let mut found = false;
+    // Each item as it will be written, or `None` once it is taken out.
+    let mut written: Vec<Option<String>> = Vec::new();
+    for item in &items {
+        let text = &src[item.value.clone()];
+        let listed = serde_json::from_str::<String>(text).is_ok_and(|k| keys.contains(&k.as_str()));
+        written.push(if text.starts_with('{') {
+            let mut inner = Vec::new();
+            set_json_map(
+                &json_items(src, item.value.start)?.0,
+                keys,
+                &literal,
+                &mut inner,
+            );
+            found |= !inner.is_empty();
+            Some(apply(text, item.value.start, inner))
+        } else if listed {
+            found = true;
+            match value {
+                HabitValue::Bool(true) => Some(text.to_owned()),
+                HabitValue::Bool(false) => None,
+                _ => Some(format!("{{{text}: {literal}}}")),
+            }
+        } else {
+            Some(text.to_owned())
+        });
+    }
+    if written.iter().any(Option::is_none) {
+        let (first, last) = (&items[0], &items[items.len() - 1]);
+        let kept: Vec<String> = written.into_iter().flatten().collect();
+        let separator = format!(",{}", json_lead(src, &items));
+        edits.push((first.value.start..last.value.end, kept.join(&separator)));
+    } else {
+        for (item, text) in items.iter().zip(written) {
+            let text = text.unwrap_or_default();
+            if text != src[item.value.clone()] {
+                edits.push((item.value.clone(), text));
+            }
+        }
+    }
+    if !found {
+        let name = json_string(keys[0]);
+        match value {
+            HabitValue::Bool(false) => {}
+            HabitValue::Bool(true) => json_push(src, edits, &items, close, name),
+            _ => json_push(src, edits, &items, close, format!("{{{name}: {literal}}}")),
+        }
+    }
+    Ok(())
+}
+
+// One item of a JSON map or list: the whitespace before it, its key if it has
+// one, and its value.
+struct JsonItem {
+    lead: Range<usize>,
+    key: Option<String>,
+    value: Range<usize>,
+}
+
+fn skip_space(src: &str, at: usize) -> usize {
+    src.len() - src[at..].trim_start().len()
+}
+
+// Where the JSON value starting at `at` ends.
+fn json_end(src: &str, at: usize) -> Result<usize, String> {
+    let mut values = serde_json::Deserializer::from_str(&src[at..]).into_iter::<IgnoredAny>();
+    match values.next() {
+        Some(Ok(_)) => Ok(at + values.byte_offset()),
+        _ => Err("unreadable JSON front matter".to_owned()),
+    }
+}
+
+// The items of the map or list opening at `open`, and where it closes.
+fn json_items(src: &str, open: usize) -> Result<(Vec<JsonItem>, usize), String> {
+    let unreadable = || "unreadable JSON front matter".to_owned();
+    let map = src[open..].starts_with('{');
+    let mut items = Vec::new();
+    let mut at = open + 1;
+    loop {
+        let start = skip_space(src, at);
+        if items.is_empty() && src[start..].starts_with(['}', ']']) {
+            return Ok((items, start));
+        }
+        let (key, value) = if map {
+            let end = json_end(src, start)?;
+            let key = serde_json::from_str(&src[start..end]).map_err(|_| unreadable())?;
+            let colon = skip_space(src, end);
+            if !src[colon..].starts_with(':') {
+                return Err(unreadable());
+            }
+            (Some(key), skip_space(src, colon + 1))
+        } else {
+            (None, start)
+        };
+        let end = json_end(src, value)?;
+        items.push(JsonItem {
+            lead: at..start,
+            key,
+            value: value..end,
+        });
+        let next = skip_space(src, end);
+        match src[next..].chars().next() {
+            Some(',') => at = next + 1,
+            Some('}' | ']') => return Ok((items, next)),
+            _ => return Err(unreadable()),
+        }
+    }
+}
+
+fn json_string(s: &str) -> String {
+    serde_json::Value::from(s).to_string()
+}
+
+fn json_literal(value: HabitValue) -> String {
+    if value.is_text() {
+        json_string(&value.to_string())
+    } else {
+        value.to_string()
+    }
+}
+
+// The whitespace between items: before the second one, or the first if it is
+// alone, so `{"a": 1}` and pretty-printed maps both keep their look.
+fn json_lead<'a>(src: &'a str, items: &[JsonItem]) -> &'a str {
+    let lead = items
+        .get(1)
+        .or(items.first())
+        .map_or("", |i| &src[i.lead.clone()]);
+    if lead.is_empty() {
+        " "
+    } else {
+        lead
+    }
+}
+
+// Adds `item` after the last of `items`, or into the empty map or list that
+// closes at `close`.
+fn json_push(
+    src: &str,
+    edits: &mut Vec<(Range<usize>, String)>,
+    items: &[JsonItem],
+    close: usize,
+    item: String,
+) {
+    match items.last() {
+        Some(last) => {
+            let end = last.value.end;
+            edits.push((end..end, format!(",{}{item}", json_lead(src, items))));
+        }
+        None => edits.push((close..close, item)),
+    }
+}
+
+// `src` with each edit's range, offset by `base`, replaced by its text.
+fn apply(src: &str, base: usize, mut edits: Vec<(Range<usize>, String)>) -> String {
+    edits.sort_by_key(|(range, _)| range.start);
+    let mut out = src.to_owned();
+    for (range, text) in edits.into_iter().rev() {
+        out.replace_range(range.start - base..range.end - base, &text);
+    }
+    out
+}
+
+// The markdown after the front matter, which edits leave alone.
+fn body(file: &str) -> Option<&str> {
+    extract(file).ok().map(|block| &file[block.body..])
+}
+
+// Parses the edited file again so a bad edit never reaches the disk.
+fn check(file: &str, keys: &[&str], value: HabitValue) -> Result<(), String> {
+    let block = extract(file)?;
+    let fm: Fm = block
+        .parse(Path::new(""))
+        .map_err(|e| format!("edit would break the front matter: {}", e.reason))?;
+    let recorded: Vec<HabitValue> = keys
+        .iter()
+        .filter_map(|k| fm.habits.get(*k))
+        .copied()
+        .collect();
+    let absent_ok = value == HabitValue::Bool(false);
+    if recorded.iter().all(|&v| v == value) && (absent_ok || !recorded.is_empty()) {
+        Ok(())
+    } else {
+        Err("edit did not take effect".to_owned())
+    }
+}
+
+// Writes next to the destination and renames over it, so readers never see a
+// half-written file.
+pub(crate) fn write_atomic(path: &Path, contents: &str) -> io::Result<()> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    fs::create_dir_all(dir)?;
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let tmp = dir.join(format!(".{name}.ribbit-tmp"));
+    let mut file = fs::File::create(&tmp)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+    if let Ok(metadata) = fs::metadata(path) {
+        fs::set_permissions(&tmp, metadata.permissions())?;
+    }
+    fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Habits;
+
+    const YES: HabitValue = HabitValue::Bool(true);
+    const NO: HabitValue = HabitValue::Bool(false);
+
+    // Sets `keys` in an entry holding `habits` after a title and date,
+    // checks the result parses back with the value and returns the habits.
+    fn edit(fence: &str, head: &str, habits: &str, keys: &[&str], value: HabitValue) -> String {
+        let file = format!("{fence}\n{head}{habits}{fence}\nbody\n");
+        let edited = set_habit(&file, keys, value).unwrap();
+        check(&edited, keys, value).unwrap();
+        let block = extract(&edited).unwrap();
+        assert_eq!(&edited[block.body..], "body\n");
+        block.src[head.len()..].to_owned()
+    }
+
+    fn yaml(habits: &str, keys: &[&str], value: HabitValue) -> String {
+        edit("---", "title: t\ndate: 2024-01-01\n", habits, keys, value)
+    }
+
+    fn toml(habits: &str, keys: &[&str], value: HabitValue) -> String {
+        edit(
+            "+++",
+            "title = \"t\"\ndate = 2024-01-01\n",
+            habits,
+            keys,
+            value,
+        )
+    }
+
+    #[test]
+    fn yaml_map() {
+        let habits = "habits:\n  read: false\n  run: true\n";
+        assert_eq!(
+            yaml(habits, &["read"], YES),
+            "habits:\n  read: true\n  run: true\n"
+        );
+        assert_eq!(
+            yaml(habits, &["write"], HabitValue::Number(2.0)),
+            "habits:\n  read: false\n  run: true\n  write: 2\n"
+        );
+    }
+
+    #[test]
+    fn yaml_list_of_maps() {
+        let habits = "habits:\n  - read: false\n  - run: true\n";
+        assert_eq!(
+            yaml(habits, &["read"], HabitValue::Duration(20.0)),
+            "habits:\n  - read: 20m\n  - run: true\n"
+        );
+        assert_eq!(
+            yaml(habits, &["write"], YES),
+            "habits:\n  - read: false\n  - run: true\n  - write: true\n"
+        );
+    }
+
+    #[test]
+    fn yaml_names() {
+        let habits = "habits:\n  - read\n  - run\n";
+        assert_eq!(
+            yaml(habits, &["write"], YES),
+            "habits:\n  - read\n  - run\n  - write\n"
+        );
+        assert_eq!(yaml(habits, &["read"], NO), "habits:\n  - run\n");
+        assert_eq!(
+            yaml(habits, &["run"], HabitValue::Number(3.0)),
+            "habits:\n  - read\n  - run: 3\n"
+        );
+    }
+
+    #[test]
+    fn yaml_flow() {
+        assert_eq!(
+            yaml("habits: {read: false, run: true}\n", &["read"], YES),
+            "habits: {read: true, run: true}\n"
+        );
+        assert_eq!(
+            yaml("habits: {read: false}\n", &["run"], YES),
+            "habits: {read: false, run: true}\n"
+        );
+        assert_eq!(
+            yaml("habits: [read]\n", &["run"], YES),
+            "habits: [read, run]\n"
+        );
+        assert_eq!(
+            yaml("habits: [read, run]\n", &["read"], NO),
+            "habits: [run]\n"
+        );
+    }
+
+    #[test]
+    fn yaml_without_habits() {
+        assert_eq!(yaml("", &["read"], YES), "habits:\n  read: true\n");
+        assert_eq!(
+            yaml("habits: ~\n", &["read"], YES),
+            "habits:\n  read: true\n"
+        );
+    }
+
+    #[test]
+    fn toml_table() {
+        let habits = "[habits]\nread = false\n";
+        assert_eq!(
+            toml(habits, &["read"], HabitValue::Duration(45.0)),
+            "[habits]\nread = \"45m\"\n"
+        );
+        assert_eq!(
+            toml(habits, &["run"], YES),
+            "[habits]\nread = false\nrun = true\n"
+        );
+        assert_eq!(toml("", &["read"], YES), "[habits]\nread = true\n");
+    }
+
+    #[test]
+    fn toml_inline_table() {
+        assert_eq!(
+            toml("habits = { read = false }\n", &["run"], YES),
+            "habits = { read = false, run = true }\n"
+        );
+        assert_eq!(
+            toml("habits = { read = false }\n", &["read"], HabitValue::Skip),
+            "habits = { read = \"skip\" }\n"
+        );
+    }
+
+    #[test]
+    fn crlf() {
+        let file = "---\r\ntitle: t\r\ndate: 2024-01-01\r\nhabits:\r\n  read: false\r\n---\r\n";
+        assert_eq!(
+            set_habit(file, &["read"], YES).unwrap(),
+            "---\r\ntitle: t\r\ndate: 2024-01-01\r\nhabits:\r\n  read: true\r\n---\r\n"
+        );
+        assert_eq!(
+            set_habit(file, &["run"], YES).unwrap(),
+            "---\r\ntitle: t\r\ndate: 2024-01-01\r\nhabits:\r\n  read: false\r\n  run: true\r\n---\r\n"
+        );
+    }
+
+    #[test]
+    fn trailing_comments() {
+        assert_eq!(
+            yaml("habits: # today\n  read: false # morning\n", &["read"], YES),
+            "habits: # today\n  read: true # morning\n"
+        );
+        assert_eq!(
+            toml("[habits]\nread = false # morning\n", &["read"], YES),
+            "[habits]\nread = true # morning\n"
+        );
+        assert_eq!(
+            yaml("habits: {read: false} # today\n", &["read"], YES),
+            "habits: {read: true} # today\n"
+        );
+    }
+
+    #[test]
+    fn alias_keys() {
+        let keys = ["read", "reading"];
+        assert_eq!(
+            yaml("habits:\n  reading: false\n", &keys, YES),
+            "habits:\n  reading: true\n"
+        );
+        assert_eq!(yaml("habits:\n  - reading\n", &keys, NO), "habits:\n");
+        assert_eq!(
+            toml("[habits]\n\"reading\" = false\n", &keys, YES),
+            "[habits]\n\"reading\" = true\n"
+        );
+    }
+
+    // Sets `keys` in a JSON entry holding `habits`, checks the result parses
+    // back with the value and returns the edited object.
+    fn json(habits: &str, keys: &[&str], value: HabitValue) -> String {
+        let file = format!("{{\"title\": \"t\", \"date\": \"2024-01-01\"{habits}}}\nbody\n");
+        let edited = set_habit(&file, keys, value).unwrap();
+        check(&edited, keys, value).unwrap();
+        let block = extract(&edited).unwrap();
+        assert_eq!(&edited[block.body..], "\nbody\n");
+        block.src.to_owned()
+    }
+
+    #[test]
+    fn json_map() {
+        let habits = ", \"habits\": {\"read\": false, \"run\": true}";
+        assert_eq!(
+            json(habits, &["read"], YES),
+            r#"{"title": "t", "date": "2024-01-01", "habits": {"read": true, "run": true}}"#
+        );
+        assert_eq!(
+            json(habits, &["write"], HabitValue::Duration(45.0)),
+            r#"{"title": "t", "date": "2024-01-01", "habits": {"read": false, "run": true, "write": "45m"}}"#
+        );
+        assert_eq!(
+            json("", &["read"], YES),
+            r#"{"title": "t", "date": "2024-01-01", "habits": {"read": true}}"#
+        );
+        assert_eq!(
+            json(", \"habits\": null", &["read"], YES),
+            r#"{"title": "t", "date": "2024-01-01", "habits": {"read": true}}"#
+        );
+        assert_eq!(
+            json(", \"habits\": {}", &["read"], HabitValue::Skip),
+            r#"{"title": "t", "date": "2024-01-01", "habits": {"read": "skip"}}"#
+        );
+    }
+
+    #[test]
+    fn json_pretty() {
+        let file = "{\n  \"title\": \"t\",\n  \"date\": \"2024-01-01\",\n  \"habits\": {\n    \"read\": false\n  }\n}\n";
+        assert_eq!(
+            set_habit(file, &["run"], HabitValue::Number(3.0)).unwrap(),
+            "{\n  \"title\": \"t\",\n  \"date\": \"2024-01-01\",\n  \"habits\": {\n    \"read\": false,\n    \"run\": 3\n  }\n}\n"
+        );
+    }
+
+    #[test]
+    fn json_list() {
+        let habits = ", \"habits\": [\"read\", {\"run\": 2}]";
+        let list =
+            |list: &str| format!(r#"{{"title": "t", "date": "2024-01-01", "habits": {list}}}"#);
+        assert_eq!(
+            json(habits, &["write"], YES),
+            list(r#"["read", {"run": 2}, "write"]"#)
+        );
+        assert_eq!(json(habits, &["read"], NO), list(r#"[{"run": 2}]"#));
+        assert_eq!(
+            json(habits, &["run"], HabitValue::Number(5.0)),
+            list(r#"["read", {"run": 5}]"#)
+        );
+        assert_eq!(
+            json(habits, &["read"], HabitValue::Number(5.0)),
+            list(r#"[{"read": 5}, {"run": 2}]"#)
+        );
+        assert_eq!(
+            json(habits, &["swim"], HabitValue::Number(1.0)),
+            list(r#"["read", {"run": 2}, {"swim": 1}]"#)
+        );
+        assert_eq!(json(", \"habits\": [\"read\"]", &["read"], NO), list("[]"));
+        assert_eq!(
+            json(
+                ", \"habits\": [\"reading\", \"run\"]",
+                &["read", "reading"],
+                NO
+            ),
+            list(r#"["run"]"#)
+        );
+    }
+
+    #[test]
+    fn names_cannot_hold_amounts_in_flow() {
+        let file = "---\ntitle: t\ndate: 2024-01-01\nhabits: [read]\n---\n";
+        assert!(set_habit(file, &["read"], HabitValue::Number(2.0)).is_err());
+    }
+
+    // Fills `template` for 2024-01-19 and parses it back.
+    fn filled(template: &str) -> Fm {
+        let config: Config = ::toml::from_str("[habits.read]\n[habits.\"long walk\"]").unwrap();
+        let file = fill(template, &config, "2024-01-19".parse().unwrap());
+        extract(&file).unwrap().parse(Path::new("")).unwrap()
+    }
+
+    #[test]
+    fn templates() {
+        let habits = Habits::from([("long walk".to_owned(), NO), ("read".to_owned(), NO)]);
+        let yaml = filled(TEMPLATE);
+        assert_eq!(yaml.title, "Friday, January 19, 2024");
+        assert_eq!(yaml.habits, habits);
+        let toml = filled("+++\ntitle = \"{title}\"\ndate = {date}\n[habits]\n{habits}+++\n");
+        assert_eq!(toml.habits, habits);
+        let json =
+            filled("{\"title\": \"{title}\", \"date\": \"{date}\", \"habits\": {{habits}}}\n");
+        assert_eq!(json.habits, habits);
+    }
+}
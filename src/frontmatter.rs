@@ -0,0 +1,296 @@
+use std::{
+    fmt,
+    fs::read_to_string,
+    path::{Path, PathBuf},
+};
+
+use serde::{
+    de::{DeserializeOwned, IgnoredAny},
+    Serialize,
+};
+
+use crate::Fm;
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Diagnostic {
+    pub(crate) path: PathBuf,
+    pub(crate) line: Option<usize>,
+    pub(crate) column: Option<usize>,
+    pub(crate) reason: String,
+}
+
+impl Diagnostic {
+    pub(crate) fn new(
+        path: &Path,
+        location: Option<(usize, usize)>,
+        reason: impl ToString,
+    ) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            line: location.map(|(line, _)| line),
+            column: location.map(|(_, column)| column),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => {
+                write!(
+                    f,
+                    "{}:{line}:{column}: {}",
+                    self.path.display(),
+                    self.reason
+                )
+            }
+            _ => write!(f, "{}: {}", self.path.display(), self.reason),
+        }
+    }
+}
+
+// Supported front matter formats, told apart by the first line of the file:
+// YAML between `---` fences, TOML between `+++` fences, or a bare JSON object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Format {
+    Yaml,
+    Toml,
+    Json,
+}
+
+impl Format {
+    pub(crate) fn detect(first_line: &str) -> Option<Self> {
+        match first_line.trim_end() {
+            "---" => Some(Self::Yaml),
+            "+++" => Some(Self::Toml),
+            line if line.starts_with('{') => Some(Self::Json),
+            _ => None,
+        }
+    }
+
+    fn fence(self) -> Option<&'static str> {
+        match self {
+            Self::Yaml => Some("---"),
+            Self::Toml => Some("+++"),
+            Self::Json => None,
+        }
+    }
+
+    // Deserialises `src`, returning the error message and its 1-based
+    // line/column within `src` on failure.
+    fn parse<T: DeserializeOwned>(self, src: &str) -> Result<T, (Option<(usize, usize)>, String)> {
+        match self {
+            Self::Yaml => serde_yaml::from_str(src).map_err(|e| {
+                let location = e.location().map(|loc| (loc.line(), loc.column()));
+                (location, strip_location(&e.to_string(), " at line "))
+            }),
+            Self::Toml => {
+                let value = toml::from_str::<toml::Value>(src).map_err(|e| {
+                    let location = e.span().map(|span| line_column(src, span.start));
+                    (location, e.message().trim().replace('\n', ", "))
+                })?;
+                serde_json::from_value(toml_to_json(value)).map_err(|e| (None, e.to_string()))
+            }
+            Self::Json => serde_json::from_str(src).map_err(|e| {
+                let location = Some((e.line(), e.column()));
+                (location, strip_location(&e.to_string(), " at line "))
+            }),
+        }
+    }
+}
+
+// Parsers append their own location to the message; ours points into the file
+// instead.
+fn strip_location(message: &str, marker: &str) -> String {
+    message.split(marker).next().unwrap_or(message).to_owned()
+}
+
+fn line_column(src: &str, offset: usize) -> (usize, usize) {
+    let before = &src[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map_or(0, |n| n + 1) + 1;
+    (line, column)
+}
+
+// TOML dates have no JSON counterpart, so they become strings, which is what
+// the rest of the model expects.
+fn toml_to_json(value: toml::Value) -> serde_json::Value {
+    match value {
+        toml::Value::String(s) => s.into(),
+        toml::Value::Integer(i) => i.into(),
+        toml::Value::Float(f) => f.into(),
+        toml::Value::Boolean(b) => b.into(),
+        toml::Value::Datetime(d) => d.to_string().into(),
+        toml::Value::Array(a) => a.into_iter().map(toml_to_json).collect(),
+        toml::Value::Table(t) => t.into_iter().map(|(k, v)| (k, toml_to_json(v))).collect(),
+    }
+}
+
+// The front matter block at the top of a file: its source, which format it is
+// in, and the byte offsets where the source and the markdown body start.
+pub(crate) struct Block<'a> {
+    pub(crate) format: Format,
+    pub(crate) src: &'a str,
+    pub(crate) start: usize,
+    pub(crate) body: usize,
+}
+
+impl Block<'_> {
+    // File line of the first source line: fenced blocks start below the fence.
+    fn first_line(&self) -> usize {
+        match self.format.fence() {
+            Some(_) => 2,
+            None => 1,
+        }
+    }
+
+    // The file line of the first source line whose key starts with `needle`.
+    pub(crate) fn line_of(&self, needle: &str) -> Option<usize> {
+        self.src
+            .lines()
+            .position(|line| line.trim_start_matches([' ', '-', '"']).starts_with(needle))
+            .map(|n| n + self.first_line())
+    }
+
+    pub(crate) fn parse<T: DeserializeOwned>(&self, path: &Path) -> Result<T, Diagnostic> {
+        self.format.parse(self.src).map_err(|(location, reason)| {
+            let location = location.map(|(line, column)| (line + self.first_line() - 1, column));
+            Diagnostic::new(path, location, reason)
+        })
+    }
+}
+
+// Only a block opened on the very first line counts as front matter, so
+// horizontal rules in the body are left alone. A leading BOM and CRLF line
+// endings are tolerated.
+pub(crate) fn extract(file: &str) -> Result<Block<'_>, &'static str> {
+    let mut offset = file.len() - file.trim_start_matches('\u{feff}').len();
+    let mut lines = file[offset..].split_inclusive('\n');
+    let first = lines.next().ok_or("no front matter")?;
+    let format = Format::detect(first).ok_or("no front matter")?;
+
+    let Some(fence) = format.fence() else {
+        // A JSON block ends where its top-level object does. If the object is
+        // malformed, hand over the rest of the file so parsing reports where.
+        let rest = &file[offset..];
+        let mut objects = serde_json::Deserializer::from_str(rest).into_iter::<IgnoredAny>();
+        return match objects.next() {
+            Some(Ok(_)) => Ok(Block {
+                format,
+                src: &rest[..objects.byte_offset()],
+                start: offset,
+                body: offset + objects.byte_offset(),
+            }),
+            Some(Err(e)) if !e.is_eof() => Ok(Block {
+                format,
+                src: rest,
+                start: offset,
+                body: file.len(),
+            }),
+            _ => Err("unterminated front matter"),
+        };
+    };
+
+    offset += first.len();
+    let start = offset;
+    for line in lines {
+        if line.trim_end() == fence {
+            return Ok(Block {
+                format,
+                src: &file[start..offset],
+                start,
+                body: offset + line.len(),
+            });
+        }
+        offset += line.len();
+    }
+    Err("unterminated front matter")
+}
+
+fn parse_file(path: &Path) -> Result<Fm, Diagnostic> {
+    let file = read_to_string(path).map_err(|e| Diagnostic::new(path, None, e))?;
+    let block = extract(&file).map_err(|e| Diagnostic::new(path, None, e))?;
+    let mut fm: Fm = block.parse(path)?;
+    fm.path = path.to_path_buf();
+    Ok(fm)
+}
+
+pub(crate) fn parse_frontmatter(md_files: Vec<PathBuf>) -> (Vec<Fm>, Vec<Diagnostic>) {
+    let mut fms = Vec::new();
+    let mut diagnostics = Vec::new();
+    for path in &md_files {
+        match parse_file(path) {
+            Ok(fm) => fms.push(fm),
+            Err(diagnostic) => diagnostics.push(diagnostic),
+        }
+    }
+    (fms, diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn yaml() {
+        let file = "---\ndate: 2024-01-01\n---\nbody\n---\n";
+        let block = extract(file).unwrap();
+        assert_eq!(block.format, Format::Yaml);
+        assert_eq!(block.src, "date: 2024-01-01\n");
+        assert_eq!(&file[block.body..], "body\n---\n");
+    }
+
+    #[test]
+    fn toml_with_bom_and_crlf() {
+        let file = "\u{feff}+++\r\ndate = 2024-01-01\r\n+++\r\nbody";
+        let block = extract(file).unwrap();
+        assert_eq!(block.format, Format::Toml);
+        assert_eq!(block.src, "date = 2024-01-01\r\n");
+        assert_eq!(&file[block.start..block.start + block.src.len()], block.src);
+        assert_eq!(&file[block.body..], "body");
+    }
+
+    #[test]
+    fn json() {
+        let file = "{\"date\": \"2024-01-01\", \"habits\": {}}\nbody";
+        let block = extract(file).unwrap();
+        assert_eq!(block.format, Format::Json);
+        assert_eq!(block.src, "{\"date\": \"2024-01-01\", \"habits\": {}}");
+        assert_eq!(&file[block.body..], "\nbody");
+    }
+
+    #[test]
+    fn malformed_json_keeps_the_rest() {
+        let file = "{\"date\": }\nbody";
+        assert_eq!(extract(file).unwrap().src, file);
+    }
+
+    #[test]
+    fn missing() {
+        assert_eq!(extract("").err(), Some("no front matter"));
+        assert_eq!(extract("# title\n---\n").err(), Some("no front matter"));
+        assert_eq!(
+            extract("---\ndate: 2024-01-01\n").err(),
+            Some("unterminated front matter")
+        );
+        assert_eq!(
+            extract("{\"date\": ").err(),
+            Some("unterminated front matter")
+        );
+    }
+
+    #[test]
+    fn locations() {
+        let file = "---\ntitle: t\ndate: 2024-01-01\nhabits:\n  read: maybe\n---\n";
+        let block = extract(file).unwrap();
+        assert_eq!(block.line_of("habits"), Some(4));
+        let e = block.parse::<Fm>(Path::new("a.md")).err().unwrap();
+        assert_eq!((e.line, e.column), (Some(5), Some(9)));
+        assert!(
+            e.reason.contains("`maybe` is not a boolean"),
+            "{}",
+            e.reason
+        );
+    }
+}
@@ -0,0 +1,462 @@
+use std::{
+    collections::BTreeSet,
+    fmt,
+    io::{self, Write},
+};
+
+use chrono::{Datelike, NaiveDate, Weekday};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Config,
+    count,
+    output::Report,
+    percent,
+    period::Period,
+    range::DateRange,
+    schedule::{parse_weekdays, HabitDays},
+    value::{HabitValue, Polarity, Stats},
+    Fm,
+};
+
+// What a goal asks for in each period: days done, or a total amount.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Target {
+    Times(u32),
+    Amount(f64),
+}
+
+// A target per period, optionally counting only some days of the week, e.g.
+// `4x per week`, `20 pages per day` or `daily on weekdays`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub(crate) struct Goal {
+    text: String,
+    target: Target,
+    per: Period,
+    on: Option<Vec<Weekday>>,
+}
+
+impl TryFrom<String> for Goal {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, String> {
+        parse_goal(&s)
+    }
+}
+
+impl fmt::Display for Goal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+impl Goal {
+    // Whether `date` counts towards the goal: a day the habit is due on and,
+    // if the goal names days, one of those.
+    fn counts(&self, date: NaiveDate, due: &HabitDays) -> bool {
+        due.due(date)
+            && self
+                .on
+                .as_ref()
+                .is_none_or(|on| on.contains(&date.weekday()))
+    }
+}
+
+// Accepts `daily`, `weekdays`, `<N>x per <period>`, `<N> times a <period>`
+// and `<amount> [unit] per <period>` (or `/<period>`), any of them followed by
+// `on weekdays`, `on weekends` or `on mon,wed,fri`.
+pub(crate) fn parse_goal(s: &str) -> Result<Goal, String> {
+    let text = s.trim().to_lowercase();
+    let (quantity, on) = match text.split_once(" on ") {
+        Some((quantity, on)) => (quantity.trim(), Some(parse_weekdays(on)?)),
+        None => (text.as_str(), None),
+    };
+    let daily = |on| Goal {
+        text: text.clone(),
+        target: Target::Times(1),
+        per: Period::Day,
+        on,
+    };
+    match quantity {
+        "daily" | "every day" => return Ok(daily(on)),
+        "weekdays" | "weekends" => return Ok(daily(Some(parse_weekdays(quantity)?))),
+        _ => {}
+    }
+
+    let (amount, per) = quantity
+        .rsplit_once(" per ")
+        .or_else(|| quantity.rsplit_once(" a "))
+        .or_else(|| quantity.rsplit_once('/'))
+        .ok_or_else(|| format!("goal `{s}` should look like `4x per week` or `20 pages/day`"))?;
+    let per = match per.trim() {
+        "daily" => Period::Day,
+        per => Period::from_str(per, true).map_err(|_| format!("unknown period `{per}`"))?,
+    };
+    let amount = amount.trim();
+    let times = amount
+        .strip_suffix('x')
+        .or_else(|| amount.strip_suffix(" times"))
+        .and_then(|n| n.trim().parse().ok());
+    let target = match times {
+        Some(times) => Target::Times(times),
+        None => {
+            let first = amount.split_whitespace().next().unwrap_or_default();
+            match HabitValue::parse(first)? {
+                HabitValue::Bool(_) | HabitValue::Skip => {
+                    return Err(format!("goal `{s}` has no amount"))
+                }
+                value => Target::Amount(value.amount().unwrap_or_default()),
+            }
+        }
+    };
+    Ok(Goal {
+        text: text.clone(),
+        target,
+        per,
+        on,
+    })
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub(crate) struct PeriodResult {
+    start: NaiveDate,
+    end: NaiveDate,
+    progress: f64,
+    target: f64,
+    met: bool,
+    skipped: usize,
+}
+
+// How one period went, or `None` for a daily goal on a day it skips.
+fn evaluate(
+    fm: &[Fm],
+    habit: &str,
+    goal: &Goal,
+    config: &Config,
+    range: DateRange,
+) -> Option<PeriodResult> {
+    let due = config.days(habit);
+    let (start, end) = (range.start?, range.end?);
+    if goal.per == Period::Day && !goal.counts(start, &due) {
+        return None;
+    }
+    let from = fm.partition_point(|f| f.date < start);
+    let to = fm.partition_point(|f| f.date <= end);
+    let names = BTreeSet::from([habit.to_owned()]);
+    let fm: Vec<&Fm> = fm[from..to]
+        .iter()
+        .filter(|f| goal.counts(f.date, &due))
+        .collect();
+    let count = count(fm.iter().copied(), &names, config);
+    // A habit to avoid counts its clean days towards a number of times, and
+    // an amount of it is a limit to stay under.
+    let done = count.kept(&due);
+    let (progress, target) = match goal.target {
+        Target::Times(times) => {
+            let done = u32::try_from(done).unwrap_or(u32::MAX);
+            (f64::from(done), f64::from(times))
+        }
+        Target::Amount(amount) => (count.amounts.get(habit).map_or(0.0, |s| s.sum), amount),
+    };
+    let met = match (goal.target, due.polarity) {
+        (Target::Amount(_), Polarity::Avoid) => progress <= target,
+        _ => progress >= target,
+    };
+    Some(PeriodResult {
+        start,
+        end,
+        progress,
+        target,
+        met,
+        skipped: due.skipped_days(start, end),
+    })
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct GoalStatus {
+    habit: String,
+    goal: String,
+    per: Period,
+    current: Option<PeriodResult>,
+    days_left: usize,
+    met: usize,
+    periods: usize,
+    history: Vec<PeriodResult>,
+    #[serde(skip)]
+    unit: Option<String>,
+    #[serde(skip)]
+    stats: Stats,
+    #[serde(skip)]
+    target: Target,
+    #[serde(skip)]
+    avoid: bool,
+}
+
+// Every period from the one holding the first entry up to the current one.
+// Only finished periods count towards the history.
+fn status(
+    fm: &[Fm],
+    habit: &str,
+    goal: &Goal,
+    config: &Config,
+    today: NaiveDate,
+    week_start: Weekday,
+) -> GoalStatus {
+    let first = fm.first().map_or(today, |f| f.date.min(today));
+    let mut start = goal.per.start(first, week_start);
+    let mut history = Vec::new();
+    let mut current = None;
+    let due = config.days(habit);
+    while start <= today {
+        let range = goal.per.containing(start, week_start);
+        let result = evaluate(fm, habit, goal, config, range);
+        if range.contains(today) {
+            current = result;
+        } else {
+            history.extend(result);
+        }
+        start = goal.per.advance(start, 1);
+    }
+    let end = goal.per.containing(today, week_start).end.unwrap_or(today);
+    let stats = count(fm, &BTreeSet::from([habit.to_owned()]), config)
+        .amounts
+        .get(habit)
+        .copied()
+        .unwrap_or_default();
+    GoalStatus {
+        habit: habit.to_owned(),
+        goal: goal.to_string(),
+        per: goal.per,
+        current,
+        days_left: today
+            .iter_days()
+            .take_while(|&d| d <= end)
+            .filter(|&d| goal.counts(d, &due))
+            .count(),
+        met: history.iter().filter(|p| p.met).count(),
+        periods: history.len(),
+        history,
+        unit: config.unit(habit).map(str::to_owned),
+        stats,
+        target: goal.target,
+        avoid: due.avoid(),
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct GoalRow {
+    habit: String,
+    goal: String,
+    progress: Option<f64>,
+    target: Option<f64>,
+    days_left: usize,
+    met_now: bool,
+    met: usize,
+    periods: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct GoalsReport {
+    date: NaiveDate,
+    goals: Vec<GoalStatus>,
+    #[serde(skip)]
+    history: bool,
+}
+
+pub(crate) fn goals(
+    fm: &[Fm],
+    names: &BTreeSet<String>,
+    config: &Config,
+    today: NaiveDate,
+    week_start: Weekday,
+    history: bool,
+) -> GoalsReport {
+    let goals = names
+        .iter()
+        .filter_map(|habit| Some((habit, config.habits.get(habit)?.goal.as_ref()?)))
+        .map(|(habit, goal)| status(fm, habit, goal, config, today, week_start))
+        .collect();
+    GoalsReport {
+        date: today,
+        goals,
+        history,
+    }
+}
+
+const fn this(per: Period) -> &'static str {
+    match per {
+        Period::Day => "today",
+        Period::Week => "this week",
+        Period::Month => "this month",
+        Period::Quarter => "this quarter",
+        Period::Year => "this year",
+    }
+}
+
+const fn plural(per: Period) -> &'static str {
+    match per {
+        Period::Day => "days",
+        Period::Week => "weeks",
+        Period::Month => "months",
+        Period::Quarter => "quarters",
+        Period::Year => "years",
+    }
+}
+
+impl GoalStatus {
+    fn progress(&self, result: &PeriodResult) -> String {
+        match self.target {
+            Target::Times(_) => {
+                let clean = if self.avoid { " clean" } else { "" };
+                format!("{}/{}{clean}", result.progress, result.target)
+            }
+            Target::Amount(_) => {
+                let unit = self.unit.as_deref();
+                format!(
+                    "{}/{}",
+                    self.stats.format(result.progress, None),
+                    self.stats.format(result.target, unit)
+                )
+            }
+        }
+    }
+
+    // Whether the target is a limit rather than something to reach.
+    const fn limit(&self) -> bool {
+        self.avoid && matches!(self.target, Target::Amount(_))
+    }
+}
+
+impl Report for GoalsReport {
+    type Row = GoalRow;
+
+    fn rows(&self) -> Vec<GoalRow> {
+        self.goals
+            .iter()
+            .map(|g| GoalRow {
+                habit: g.habit.clone(),
+                goal: g.goal.clone(),
+                progress: g.current.map(|c| c.progress),
+                target: g.current.map(|c| c.target),
+                days_left: g.days_left,
+                met_now: g.current.is_some_and(|c| c.met),
+                met: g.met,
+                periods: g.periods,
+            })
+            .collect()
+    }
+
+    fn table(&self, out: &mut dyn Write) -> io::Result<()> {
+        let width = self.goals.iter().map(|g| g.habit.len()).max().unwrap_or(0);
+        let goal_width = self.goals.iter().map(|g| g.goal.len()).max().unwrap_or(0);
+        for g in &self.goals {
+            let mut current = match g.current {
+                None => format!("nothing due {}", this(g.per)),
+                Some(c) if g.limit() => {
+                    let state = if c.met { "within limit" } else { "over limit" };
+                    format!("{} {}, {state}", g.progress(&c), this(g.per))
+                }
+                Some(c) if c.met => format!("{} {}, met", g.progress(&c), this(g.per)),
+                Some(c) if g.per == Period::Day => format!("{} {}", g.progress(&c), this(g.per)),
+                Some(c) => {
+                    let left = match g.days_left {
+                        0 => "no days left".to_owned(),
+                        1 => "1 day left".to_owned(),
+                        n => format!("{n} days left"),
+                    };
+                    format!("{} {}, {left}", g.progress(&c), this(g.per))
+                }
+            };
+            if let Some(c) = g.current.filter(|c| c.skipped > 0) {
+                current.push_str(&format!(", {} skipped", c.skipped));
+            }
+            writeln!(
+                out,
+                "{:width$}  {:goal_width$}  {current:32}  met {} of {} {} ({:.0}%)",
+                g.habit,
+                g.goal,
+                g.met,
+                g.periods,
+                plural(g.per),
+                percent(g.met, g.periods)
+            )?;
+            if self.history {
+                for p in &g.history {
+                    let mark = if p.met { '✓' } else { '✗' };
+                    let span = if p.start == p.end {
+                        p.start.to_string()
+                    } else {
+                        format!("{} to {}", p.start, p.end)
+                    };
+                    let skipped = match p.skipped {
+                        0 => String::new(),
+                        n => format!("  {n} skipped"),
+                    };
+                    writeln!(
+                        out,
+                        "{:width$}  {span:24}  {}  {mark}{skipped}",
+                        "",
+                        g.progress(p)
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn goal(s: &str) -> (Target, Period, Option<Vec<Weekday>>) {
+        let goal = parse_goal(s).unwrap();
+        (goal.target, goal.per, goal.on)
+    }
+
+    #[test]
+    fn goals() {
+        assert_eq!(goal("daily"), (Target::Times(1), Period::Day, None));
+        assert_eq!(goal("4x per week"), (Target::Times(4), Period::Week, None));
+        assert_eq!(
+            goal("3 times a Month"),
+            (Target::Times(3), Period::Month, None)
+        );
+        assert_eq!(
+            goal("20 pages per day"),
+            (Target::Amount(20.0), Period::Day, None)
+        );
+        assert_eq!(goal("2h/week"), (Target::Amount(120.0), Period::Week, None));
+        assert_eq!(
+            goal("weekdays"),
+            (
+                Target::Times(1),
+                Period::Day,
+                Some(parse_weekdays("weekdays").unwrap())
+            )
+        );
+        assert_eq!(
+            goal("2x per week on sat,sun"),
+            (
+                Target::Times(2),
+                Period::Week,
+                Some(vec![Weekday::Sat, Weekday::Sun])
+            )
+        );
+    }
+
+    #[test]
+    fn bad_goals() {
+        for s in [
+            "often",
+            "yes per week",
+            "skip per day",
+            "4x per fortnight",
+            "daily on funday",
+        ] {
+            assert!(parse_goal(s).is_err(), "{s}");
+        }
+    }
+}
@@ -1,15 +1,72 @@
-use std::{error::Error, fs::read_to_string, ops::Add, path::PathBuf};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env,
+    error::Error,
+    fmt,
+    io::{self, Write},
+    ops::Add,
+    path::PathBuf,
+};
 
 type RibbitR<T> = Result<T, Box<dyn Error>>;
 
-use chrono::{Datelike, Local};
-use clap::{Parser, ValueEnum};
-use serde::Deserialize;
+use chrono::{Local, NaiveDate};
+use clap::{
+    builder::{PossibleValue, PossibleValuesParser},
+    CommandFactory, FromArgMatches, Parser,
+};
+use serde::{
+    de::{self, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+
+use config::{Config, ConfigReport, Settings};
+use frontmatter::{parse_frontmatter, Diagnostic};
+use list::ListSort;
+use output::{emit, OutputFormat, Report};
+use range::{parse_date_expr, DateExpr, DateRange, RangeArgs};
+use schedule::HabitDays;
+use skip::Skips;
+use streak::MissingDays;
+use value::{HabitValue, Polarity, Stats};
+
+mod calendar;
+mod checkin;
+mod config;
+mod edit;
+mod frontmatter;
+mod goal;
+mod lint;
+mod list;
+mod output;
+mod period;
+mod range;
+mod schedule;
+mod skip;
+mod streak;
+mod value;
+mod window;
 
 #[derive(Parser, Debug)]
 struct Cli {
-    #[arg(default_value = "/Users/z/journal")]
-    journal_dir: PathBuf,
+    /// Journal directory, instead of $RIBBIT_JOURNAL or the config file
+    journal_dir: Option<PathBuf>,
+
+    /// Journals declared in the config to read, or `all`; repeat or comma-separate to combine
+    #[arg(short, long = "journal", global = true, value_delimiter = ',')]
+    journals: Vec<String>,
+
+    /// Config file to read instead of ~/.config/ribbit/config.toml
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Fail instead of warning when a journal file cannot be parsed
+    #[arg(long, global = true)]
+    strict: bool,
+
+    /// How to print reports
+    #[arg(short, long, global = true, value_enum)]
+    output: Option<OutputFormat>,
 
     #[command(subcommand)]
     action: Option<Action>,
@@ -18,194 +75,798 @@ struct Cli {
 #[derive(clap::Subcommand, Debug)]
 enum Action {
     Filter {
-        #[arg(value_enum)]
-        habit: Option<HabitFilter>,
+        habit: Option<String>,
+
+        #[command(flatten)]
+        range: RangeArgs,
+    },
+    /// Every matching entry with its habits
+    List {
+        /// Only entries where this habit was done
+        habit: Option<String>,
+
+        #[command(flatten)]
+        range: RangeArgs,
+
+        #[arg(long, value_enum, default_value_t)]
+        sort: ListSort,
+
+        #[arg(short, long)]
+        reverse: bool,
+    },
+    /// Counts and rates for every habit over the last 7, 30, 90 and 365 days
+    Windows {
+        /// Last day of the windows instead of today
+        #[arg(long, value_parser = parse_date_expr)]
+        at: Option<DateExpr>,
+    },
+    /// Current and longest streak for each habit
+    Streak {
+        habit: Option<String>,
+
+        /// Whether days without an entry break a streak
+        #[arg(long, value_enum)]
+        missing_days: Option<MissingDays>,
+    },
+    /// Progress towards each habit's goal this period and how often it was met
+    Goals {
+        habit: Option<String>,
+
+        /// List every finished period and whether the goal was met
+        #[arg(long)]
+        history: bool,
+    },
+    /// Heatmap of the days a habit was done, a column per week
+    #[command(alias = "heatmap")]
+    Calendar {
+        habit: String,
+
+        #[command(flatten)]
+        range: RangeArgs,
+
+        /// Plain glyphs without ANSI colours
+        #[arg(long)]
+        no_color: bool,
+    },
+    /// Mark a habit done for a day, creating the day's entry if needed
+    Log {
+        habit: String,
+
+        /// Amount to record instead of marking it done, e.g. `35` or `45m`
+        #[arg(index = 2, value_parser = HabitValue::parse, conflicts_with = "undo")]
+        value: Option<HabitValue>,
+
+        /// Day to record instead of today
+        #[arg(long, value_parser = parse_date_expr)]
+        date: Option<DateExpr>,
+
+        /// Record the habit as not done
+        #[arg(long)]
+        undo: bool,
+    },
+    /// Create the day's entry from the template
+    New {
+        /// Day to create instead of today
+        #[arg(long, value_parser = parse_date_expr)]
+        date: Option<DateExpr>,
 
-        #[arg(short, long, value_enum)]
-        time: Option<TimeFilter>,
+        /// Open the new entry in $EDITOR
+        #[arg(short, long)]
+        edit: bool,
+    },
+    /// Answer yes or no for every habit and record the answers
+    Checkin {
+        /// Day to fill in instead of today
+        #[arg(long, value_parser = parse_date_expr)]
+        date: Option<DateExpr>,
+    },
+    /// How often each habit alias was seen in the journal
+    Aliases,
+    /// Check every journal file for front matter problems
+    Lint,
+    /// Print a shell completion script
+    Completions {
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+    /// Inspect the configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
     },
 }
 
-#[derive(Debug, Clone, Copy, ValueEnum)]
-enum HabitFilter {
-    Exercise,
-    Contrib,
-    Reading,
+#[derive(clap::Subcommand, Debug)]
+enum ConfigAction {
+    /// The effective settings and where each one came from
+    Show,
+}
+
+type Habits = BTreeMap<String, HabitValue>;
+
+// `habits:` may be written as a map, a list of single-key maps or names that
+// were done, or a single name. A visitor rather than untagged enums keeps the
+// error, and its location, from whichever value is wrong.
+struct HabitsVisitor;
+
+impl<'de> Visitor<'de> for HabitsVisitor {
+    type Value = Habits;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a map of habits, a list of habits or a habit name")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Habits, E> {
+        Ok(Habits::new())
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Habits, E> {
+        Ok(Habits::new())
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, d: D) -> Result<Habits, D::Error> {
+        d.deserialize_any(self)
+    }
+
+    fn visit_str<E: de::Error>(self, name: &str) -> Result<Habits, E> {
+        Ok(Habits::from([(name.to_owned(), HabitValue::Bool(true))]))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Habits, A::Error> {
+        let mut habits = Habits::new();
+        while let Some((name, value)) = map.next_entry::<String, HabitValue>()? {
+            habits.insert(name, value);
+        }
+        Ok(habits)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Habits, A::Error> {
+        let mut habits = Habits::new();
+        while let Some(item) = seq.next_element::<HabitItem>()? {
+            habits.extend(item.0);
+        }
+        Ok(habits)
+    }
 }
 
-#[derive(Debug, Clone, Copy, ValueEnum)]
-enum TimeFilter {
-    #[value(alias("w"))]
-    Week,
-    #[value(alias("m"))]
-    Month,
-    #[value(alias("d"))]
-    Day,
-    #[value(alias("y"))]
-    Year,
+// One item of a list of habits: a name, or a map of them.
+struct HabitItem(Habits);
+
+impl<'de> Deserialize<'de> for HabitItem {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        d.deserialize_any(HabitsVisitor).map(HabitItem)
+    }
 }
 
-#[derive(Deserialize, Debug, Clone, Copy)]
-struct Habit {
-    exercise: bool,
-    contrib: bool,
-    reading: bool,
+fn deserialize_habits<'de, D: Deserializer<'de>>(d: D) -> Result<Habits, D::Error> {
+    d.deserialize_option(HabitsVisitor)
 }
 
 #[derive(Deserialize)]
 struct Fm {
     title: String,
-    date: chrono::NaiveDate,
-    habits: Habit,
-}
-
-fn filter_by_time(fm: Vec<Fm>, filter: TimeFilter) -> Vec<Fm> {
-    let today = Local::now().date_naive();
-    fm.into_iter()
-        .filter(|f| match filter {
-            TimeFilter::Day => f.date.eq(&today),
-            TimeFilter::Week => f.date.iso_week().eq(&today.iso_week()),
-            TimeFilter::Month => f.date.month().eq(&today.month()),
-            TimeFilter::Year => f.date.year().eq(&today.year()),
-        })
-        .collect()
+    date: NaiveDate,
+    #[serde(default, deserialize_with = "deserialize_habits")]
+    habits: Habits,
+    // Set when the whole day is skipped, with any reasons given.
+    #[serde(default, deserialize_with = "skip::deserialize_skip")]
+    skip: Option<Vec<String>>,
+    #[serde(skip)]
+    path: PathBuf,
+    // Name of the journal the entry was read from.
+    #[serde(skip)]
+    journal: String,
+}
+
+fn filter_by_time(fm: Vec<Fm>, range: DateRange) -> Vec<Fm> {
+    fm.into_iter().filter(|f| range.contains(f.date)).collect()
+}
+
+// The habits recorded on each day. A day with entries in several journals
+// counts once, with its values merged, so done in any of them is done.
+fn by_day<'a>(fm: impl IntoIterator<Item = &'a Fm>) -> BTreeMap<NaiveDate, Habits> {
+    let mut days: BTreeMap<NaiveDate, Habits> = BTreeMap::new();
+    for f in fm {
+        let day = days.entry(f.date).or_default();
+        for (habit, &value) in &f.habits {
+            day.entry(habit.clone())
+                .and_modify(|v| *v = v.merge(value))
+                .or_insert(value);
+        }
+    }
+    days
 }
 
-fn count(fm: Vec<Fm>) -> HabitCount {
-    fm.into_iter()
-        .take(7)
-        .fold(HabitCount::default(), |mut count: HabitCount, fm| {
-            count = count + fm.habits;
+// Completions on days a habit is not due, because it is not scheduled or the
+// day is skipped, are counted apart as a bonus. The due days that have an
+// entry are counted too, for habits to avoid.
+fn count<'a>(
+    fm: impl IntoIterator<Item = &'a Fm>,
+    names: &BTreeSet<String>,
+    config: &Config,
+) -> HabitCount {
+    by_day(fm).into_iter().fold(
+        HabitCount::new(names),
+        |mut count: HabitCount, (date, mut habits)| {
+            habits.retain(|habit, _| names.contains(habit));
+            count = count + &habits;
+            for (habit, value) in &habits {
+                if value.done() && !config.days(habit).due(date) {
+                    *count.counts.entry(habit.clone()).or_default() -= 1;
+                    *count.bonus.entry(habit.clone()).or_default() += 1;
+                }
+            }
+            for name in names.iter().filter(|name| config.days(name).due(date)) {
+                *count.entries.entry(name.clone()).or_default() += 1;
+            }
             count
-        })
+        },
+    )
+}
+
+// Folds aliased habit keys into their canonical name, counting how often each
+// alias was seen.
+fn fold_aliases(fm: &mut [Fm], config: &Config) -> AliasHits {
+    let aliases = config.aliases();
+    let mut hits = AliasHits::new();
+    for f in fm {
+        let mut habits = Habits::new();
+        for (habit, value) in std::mem::take(&mut f.habits) {
+            let habit = match aliases.get(habit.as_str()) {
+                Some(&canonical) => {
+                    *hits.entry((habit, canonical.to_owned())).or_default() += 1;
+                    canonical.to_owned()
+                }
+                None => habit,
+            };
+            habits
+                .entry(habit)
+                .and_modify(|v| *v = v.merge(value))
+                .or_insert(value);
+        }
+        f.habits = habits;
+    }
+    hits
 }
-fn count_filtered_habit(fm: Vec<Fm>, filter: HabitFilter) -> HabitCount {
-    let fm: Vec<Fm> = fm
-        .into_iter()
-        .filter(|f| match filter {
-            HabitFilter::Exercise => f.habits.exercise == true,
-            HabitFilter::Contrib => f.habits.contrib == true,
-            HabitFilter::Reading => f.habits.reading == true,
-        })
-        .collect();
 
-    count(fm)
+type AliasHits = BTreeMap<(String, String), usize>;
+
+#[derive(Debug, Clone, Serialize)]
+struct AliasRow {
+    alias: String,
+    habit: String,
+    count: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(transparent)]
+struct AliasReport(Vec<AliasRow>);
+
+impl AliasReport {
+    fn new(hits: AliasHits) -> Self {
+        Self(
+            hits.into_iter()
+                .map(|((alias, habit), count)| AliasRow {
+                    alias,
+                    habit,
+                    count,
+                })
+                .collect(),
+        )
+    }
+}
+
+impl Report for AliasReport {
+    type Row = AliasRow;
+
+    fn rows(&self) -> Vec<AliasRow> {
+        self.0.clone()
+    }
+
+    fn table(&self, out: &mut dyn Write) -> io::Result<()> {
+        for row in &self.0 {
+            writeln!(out, "{:>4} - {} -> {}", row.count, row.alias, row.habit)?;
+        }
+        Ok(())
+    }
 }
 
-fn count_filtered_time(fm: Vec<Fm>, filter: TimeFilter) -> HabitCount {
-    count(filter_by_time(fm, filter))
+// The habits declared in the config, which are also the only ones the command
+// line accepts, or every key seen in the front matter when none are.
+fn habit_names(config: &Config, fm: &[Fm]) -> BTreeSet<String> {
+    if !config.habits.is_empty() {
+        return config.habits.keys().cloned().collect();
+    }
+    fm.iter().flat_map(|f| f.habits.keys().cloned()).collect()
+}
+
+fn cli(config: &Config) -> clap::Command {
+    let cmd = Cli::command();
+    if config.habits.is_empty() {
+        return cmd;
+    }
+    let declared: Vec<PossibleValue> = config
+        .habits
+        .iter()
+        .map(|(name, def)| PossibleValue::new(name).aliases(def.aliases.clone()))
+        .collect();
+    ["filter", "list", "streak", "goals", "calendar", "log"]
+        .into_iter()
+        .fold(cmd, |cmd, name| {
+            cmd.mut_subcommand(name, |sub| {
+                sub.mut_arg("habit", |arg| {
+                    arg.value_parser(PossibleValuesParser::new(declared.clone()))
+                })
+            })
+        })
 }
 
 pub fn run() -> RibbitR<()> {
-    let matches = Cli::parse();
+    let mut config = Config::load(config::config_arg().as_deref())?;
+    let mut cmd = cli(&config);
+    let matches = Cli::from_arg_matches(&cmd.get_matches_mut())?;
+    let settings = Settings::new(
+        &config,
+        matches.journal_dir.clone(),
+        env::var(config::JOURNAL_ENV).ok(),
+        &matches.journals,
+        matches.output,
+    )?;
+    let output = settings.output.value;
+    let week_start = settings.week_start.value;
+
+    match matches.action {
+        Some(Action::Completions { shell }) => {
+            clap_complete::generate(shell, &mut cmd, "ribbit", &mut io::stdout());
+            return Ok(());
+        }
+        Some(Action::Config {
+            action: ConfigAction::Show,
+        }) => return emit(&ConfigReport::new(&settings, &config), output),
+        _ => {}
+    }
+
     let mut files = Vec::new();
-    find_files(&mut files, matches.journal_dir)?;
+    for journal in settings.journals()? {
+        let mut paths = Vec::new();
+        find_files(&mut paths, journal.path.clone())?;
+        files.push((journal, paths));
+    }
+
+    if let Some(Action::Lint) = matches.action {
+        return lint::lint(&files, &config, output);
+    }
 
-    let mut front_matters = parse_frontmatter(files)?;
+    let mut front_matters = Vec::new();
+    let mut diagnostics = Vec::new();
+    for (journal, paths) in files {
+        let (fm, mut problems) = parse_frontmatter(paths);
+        front_matters.extend(fm.into_iter().map(|f| Fm {
+            journal: journal.name.clone(),
+            ..f
+        }));
+        diagnostics.append(&mut problems);
+    }
+    report_diagnostics(&diagnostics, front_matters.len(), matches.strict)?;
     front_matters.sort_by_key(|f| f.date);
+    let alias_hits = fold_aliases(&mut front_matters, &config);
+    let names = habit_names(&config, &front_matters);
+    config.skips = Skips::new(&front_matters, &config);
 
     match matches.action {
-        Some(Action::Filter {
-            habit: filter,
-            time,
-        }) => match (filter, time) {
-            (Some(filter), None) => {
-                let count = count_filtered_habit(front_matters, filter);
-                count.print_filtered(filter);
-            }
-            (None, None) => {
-                let count = count(front_matters);
-                count.print();
+        Some(Action::Filter { habit, range }) => {
+            let coverage_range = range.range(today(), week_start);
+            let fm = range.select(front_matters, today(), week_start);
+            let coverage = Coverage::new(&fm, coverage_range, today());
+            let fm = fm.iter().filter(|f| coverage.contains(f.date));
+            let count = count(fm, &names, &config);
+            let habit = habit.map(|habit| config.canonical(&habit).to_owned());
+            if let Some(habit) = &habit {
+                check_habit(&names, habit)?;
             }
-            (None, Some(time)) => {
-                let count = count_filtered_time(front_matters, time);
-                count.print();
+            emit(&count.report(&coverage, habit.as_deref(), &config), output)?;
+        }
+        Some(Action::List {
+            habit,
+            range,
+            sort,
+            reverse,
+        }) => {
+            let mut fm = range.select(front_matters, today(), week_start);
+            if let Some(habit) = habit {
+                let habit = config.canonical(&habit);
+                check_habit(&names, habit)?;
+                fm.retain(|f| f.habits.get(habit).is_some_and(|v| v.done()));
             }
-            (Some(filter), Some(time)) => {
-                let fm = filter_by_time(front_matters, time);
-                let count = count_filtered_habit(fm, filter);
-                count.print_filtered(filter);
+            emit(&list::list(fm, &names, sort, reverse), output)?;
+        }
+        Some(Action::Streak {
+            habit,
+            missing_days,
+        }) => {
+            let names = match habit {
+                Some(habit) => {
+                    let habit = config.canonical(&habit);
+                    check_habit(&names, habit)?;
+                    BTreeSet::from([habit.to_owned()])
+                }
+                None => names,
+            };
+            let missing = missing_days.unwrap_or(settings.missing_days.value);
+            let report = streak::streaks(&front_matters, &names, &config, missing, today());
+            emit(&report, output)?;
+        }
+        Some(Action::Goals { habit, history }) => {
+            let goals: BTreeSet<String> = config
+                .habits
+                .iter()
+                .filter(|(_, def)| def.goal.is_some())
+                .map(|(habit, _)| habit.clone())
+                .collect();
+            let goals = match habit {
+                Some(habit) => {
+                    let habit = config.canonical(&habit);
+                    if !goals.contains(habit) {
+                        return Err(format!("habit `{habit}` has no goal").into());
+                    }
+                    BTreeSet::from([habit.to_owned()])
+                }
+                None if goals.is_empty() => {
+                    return Err("no goals configured, add one like `goal = \"4x per week\"` under [habits.<name>]".into());
+                }
+                None => goals,
+            };
+            let report = goal::goals(
+                &front_matters,
+                &goals,
+                &config,
+                today(),
+                week_start,
+                history,
+            );
+            emit(&report, output)?;
+        }
+        Some(Action::Calendar {
+            habit,
+            range,
+            no_color,
+        }) => {
+            let habit = config.canonical(&habit);
+            check_habit(&names, habit)?;
+            // Without a range, show the last year like GitHub does.
+            let range = match range.range(today(), week_start) {
+                range if range == DateRange::default() => window::rolling(365, today()),
+                range => range,
+            };
+            let start = range
+                .start
+                .or_else(|| front_matters.first().map(|f| f.date))
+                .unwrap_or_else(today);
+            let end = range.end.map_or_else(today, |end| end.min(today()));
+            let colour = calendar::use_colour(no_color);
+            let report = calendar::calendar(
+                &front_matters,
+                habit,
+                &config.days(habit),
+                start,
+                end,
+                colour,
+                week_start,
+            );
+            emit(&report, output)?;
+        }
+        Some(Action::Log {
+            habit,
+            value,
+            date,
+            undo,
+        }) => {
+            let journal = settings.journal()?;
+            let habit = config.canonical(&habit);
+            let date = date.map_or_else(today, |date| date.resolve(today()));
+            front_matters.retain(|f| f.journal == journal.name);
+            let value = value.unwrap_or(HabitValue::Bool(!undo));
+            let habits = [(config.keys(habit), value)];
+            let recorded =
+                edit::record(&front_matters, &settings, &config, journal, date, &habits)?;
+            emit(&recorded, output)?;
+        }
+        Some(Action::New { date, edit }) => {
+            let journal = settings.journal()?;
+            let date = date.map_or_else(today, |date| date.resolve(today()));
+            front_matters.retain(|f| f.journal == journal.name);
+            let (path, file) = edit::new_entry(&front_matters, &settings, &config, journal, date)?;
+            edit::write_atomic(&path, &file)?;
+            emit(&edit::Recorded::created(date, path.clone()), output)?;
+            if edit {
+                edit::open_editor(&path)?;
             }
-        },
+        }
+        Some(Action::Checkin { date }) => {
+            let journal = settings.journal()?;
+            let date = date.map_or_else(today, |date| date.resolve(today()));
+            front_matters.retain(|f| f.journal == journal.name);
+            let recorded =
+                checkin::checkin(&front_matters, &settings, &config, journal, &names, date)?;
+            emit(&recorded, output)?;
+        }
+        Some(Action::Aliases) => emit(&AliasReport::new(alias_hits), output)?,
+        Some(Action::Lint | Action::Completions { .. } | Action::Config { .. }) => unreachable!(),
+        Some(Action::Windows { at }) => {
+            let end = at.map_or_else(today, |at| at.resolve(today()));
+            emit(
+                &window::windows(&front_matters, &names, end, &config),
+                output,
+            )?;
+        }
         None => {
-            let count = count(front_matters);
-            count.print();
+            let coverage = Coverage::new(&front_matters, DateRange::default(), today());
+            let fm = front_matters.iter().filter(|f| coverage.contains(f.date));
+            let count = count(fm, &names, &config);
+            emit(&count.report(&coverage, None, &config), output)?;
         }
     }
 
     Ok(())
 }
 
-fn parse_frontmatter(md_files: Vec<PathBuf>) -> RibbitR<Vec<Fm>> {
-    Ok(md_files
-        .iter()
-        .filter_map(|f| {
-            if let Ok(file) = read_to_string(f) {
-                let file = file.lines();
-                let mut fm = Vec::new();
-
-                let mut delim = false;
-                for line in file {
-                    if line == "---" {
-                        delim = !delim;
-                    } else {
-                        if delim {
-                            fm.push(line.to_owned());
-                        }
-                    }
-                }
-                if fm.len() > 0 {
-                    Some(fm)
-                } else {
-                    None
-                }
-            } else {
-                None
-            }
-        })
-        .filter_map(|fm| serde_yaml::from_str::<Fm>(fm.join("\n").as_str()).ok())
-        .collect())
+fn report_diagnostics(diagnostics: &[Diagnostic], parsed: usize, strict: bool) -> RibbitR<()> {
+    for diagnostic in diagnostics {
+        eprintln!("warning: {diagnostic}");
+    }
+    eprintln!("{parsed} entries parsed, {} skipped", diagnostics.len());
+    if strict && !diagnostics.is_empty() {
+        return Err(format!("{} journal files could not be parsed", diagnostics.len()).into());
+    }
+    Ok(())
 }
 
-#[derive(Default, Debug, Clone, Copy)]
+fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+fn check_habit(names: &BTreeSet<String>, habit: &str) -> RibbitR<()> {
+    if names.contains(habit) {
+        Ok(())
+    } else {
+        Err(format!("unknown habit `{habit}`").into())
+    }
+}
+
+// Days each habit was done, plus the amounts recorded for numeric habits.
+#[derive(Default, Debug, Clone)]
 struct HabitCount {
-    exercise: usize,
-    reading: usize,
-    contrib: usize,
+    counts: BTreeMap<String, usize>,
+    bonus: BTreeMap<String, usize>,
+    entries: BTreeMap<String, usize>,
+    amounts: BTreeMap<String, Stats>,
 }
-impl Add<Habit> for HabitCount {
+
+impl Add<&Habits> for HabitCount {
     type Output = HabitCount;
 
-    fn add(self, rhs: Habit) -> Self::Output {
+    fn add(self, rhs: &Habits) -> Self::Output {
         let mut hc = self;
-        if rhs.exercise {
-            hc.exercise += 1;
-        }
-        if rhs.contrib {
-            hc.contrib += 1;
-        }
-        if rhs.reading {
-            hc.reading += 1;
+        for (habit, &value) in rhs {
+            if value.done() {
+                *hc.counts.entry(habit.clone()).or_default() += 1;
+            }
+            if value.amount().is_some() {
+                hc.amounts.entry(habit.clone()).or_default().add(value);
+            }
         }
         hc
     }
 }
 
 impl HabitCount {
-    fn print_filtered(&self, filter: HabitFilter) {
-        match filter {
-            HabitFilter::Exercise => println!("{:>4} - exercise", self.exercise),
-            HabitFilter::Contrib => println!("{:>4} - contrib", self.contrib),
-            HabitFilter::Reading => println!("{:>4} - reading", self.reading),
+    fn new(names: &BTreeSet<String>) -> Self {
+        Self {
+            counts: names.iter().map(|name| (name.clone(), 0)).collect(),
+            bonus: BTreeMap::new(),
+            entries: BTreeMap::new(),
+            amounts: BTreeMap::new(),
+        }
+    }
+    fn get(&self, habit: &str) -> usize {
+        self.counts.get(habit).copied().unwrap_or(0)
+    }
+    // Due days the habit was kept: done, or for a habit to avoid, the days
+    // with an entry that do not have it.
+    fn kept(&self, due: &HabitDays) -> usize {
+        match due.polarity {
+            Polarity::Build => self.get(due.habit),
+            Polarity::Avoid => {
+                let entries = self.entries.get(due.habit).copied().unwrap_or(0);
+                entries.saturating_sub(self.get(due.habit))
+            }
+        }
+    }
+    fn slips(&self, due: &HabitDays) -> Option<usize> {
+        due.avoid().then(|| self.get(due.habit))
+    }
+    // Doing a habit to avoid on an off day is no bonus.
+    fn bonus(&self, due: &HabitDays) -> usize {
+        match due.polarity {
+            Polarity::Build => self.bonus.get(due.habit).copied().unwrap_or(0),
+            Polarity::Avoid => 0,
+        }
+    }
+    fn report(&self, coverage: &Coverage, only: Option<&str>, config: &Config) -> CountReport {
+        CountReport {
+            days: coverage.days,
+            entries: coverage.entries,
+            no_entry: coverage.days.saturating_sub(coverage.entries),
+            habits: self
+                .counts
+                .iter()
+                .filter(|(habit, _)| only.is_none_or(|only| only == habit.as_str()))
+                .map(|(habit, _)| {
+                    let stats = self.amounts.get(habit).copied();
+                    let unit = match (stats, config.unit(habit)) {
+                        (_, Some(unit)) => Some(unit.to_owned()),
+                        (Some(stats), None) if stats.duration => Some("min".to_owned()),
+                        _ => None,
+                    };
+                    let due = config.days(habit);
+                    let (days, entries, skipped) = coverage.due(&due);
+                    CountRow {
+                        habit: habit.clone(),
+                        count: self.kept(&due),
+                        slips: self.slips(&due),
+                        bonus: self.bonus(&due),
+                        skipped,
+                        days,
+                        entries,
+                        unit,
+                        total: stats.map(|s| s.sum),
+                        mean: stats.map(|s| s.mean()),
+                        min: stats.map(|s| s.min),
+                        max: stats.map(|s| s.max),
+                        stats,
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct CountRow {
+    habit: String,
+    count: usize,
+    // Days a habit to avoid was done.
+    slips: Option<usize>,
+    bonus: usize,
+    skipped: usize,
+    days: usize,
+    entries: usize,
+    unit: Option<String>,
+    total: Option<f64>,
+    mean: Option<f64>,
+    min: Option<f64>,
+    max: Option<f64>,
+    #[serde(skip)]
+    stats: Option<Stats>,
+}
+
+// Habit counts rated against the calendar days in the range and against the
+// days that have an entry.
+#[derive(Debug, Serialize)]
+struct CountReport {
+    days: usize,
+    entries: usize,
+    no_entry: usize,
+    habits: Vec<CountRow>,
+}
+
+impl Report for CountReport {
+    type Row = CountRow;
+
+    fn rows(&self) -> Vec<CountRow> {
+        self.habits.clone()
+    }
+
+    fn table(&self, out: &mut dyn Write) -> io::Result<()> {
+        for row in &self.habits {
+            let amounts = row.stats.map_or_else(String::new, |stats| {
+                let unit = row.unit.as_deref();
+                format!(
+                    " (total {}, mean {}, min {}, max {})",
+                    stats.format(stats.sum, unit),
+                    stats.format(stats.mean(), unit),
+                    stats.format(stats.min, unit),
+                    stats.format(stats.max, unit)
+                )
+            });
+            let mut bonus = match (row.slips, row.bonus) {
+                (Some(1), _) => " avoided, 1 slip".to_owned(),
+                (Some(slips), _) => format!(" avoided, {slips} slips"),
+                (None, 0) => String::new(),
+                (None, n) => format!(" +{n} bonus"),
+            };
+            if row.skipped > 0 {
+                bonus.push_str(&format!(", {} skipped", row.skipped));
+            }
+            writeln!(
+                out,
+                "{:>14} {:>14} of entries - {}{bonus}{amounts}",
+                ratio(row.count, row.days),
+                ratio(row.count, row.entries),
+                row.habit
+            )?;
+        }
+        writeln!(
+            out,
+            "{:>14} days with no entry",
+            format!("{}/{}", self.no_entry, self.days)
+        )?;
+        Ok(())
+    }
+}
+
+// The calendar days a report covers and how many of them have an entry.
+#[derive(Debug, Clone)]
+struct Coverage {
+    start: Option<NaiveDate>,
+    end: NaiveDate,
+    dates: BTreeSet<NaiveDate>,
+    days: usize,
+    entries: usize,
+}
+
+impl Coverage {
+    // Open-ended ranges start at the first entry, and days after today have
+    // not been missed yet, so entries dated after it are left out.
+    fn new(fm: &[Fm], range: DateRange, today: NaiveDate) -> Self {
+        let end = range.end.map_or(today, |end| end.min(today));
+        let dates: BTreeSet<NaiveDate> = fm
+            .iter()
+            .map(|f| f.date)
+            .filter(|&date| range.contains(date) && date <= end)
+            .collect();
+        let start = range.start.or_else(|| dates.first().copied());
+        let days = start.map_or(0, |start| (end - start).num_days() + 1);
+        Self {
+            start,
+            end,
+            days: usize::try_from(days).unwrap_or(0),
+            entries: dates.len(),
+            dates,
         }
     }
-    fn print(&self) {
-        println!("{:>4} - exercise", self.exercise);
-        println!("{:>4} - contrib", self.contrib);
-        println!("{:>4} - reading", self.reading);
+
+    // The days a habit is due, those of them with an entry, and the skipped
+    // days left out of both.
+    fn due(&self, days: &HabitDays) -> (usize, usize, usize) {
+        let (due, skipped) = self.start.map_or((0, 0), |start| {
+            (
+                days.due_days(start, self.end),
+                days.skipped_days(start, self.end),
+            )
+        });
+        let entries = self.dates.iter().filter(|&&d| days.due(d)).count();
+        (due, entries, skipped)
+    }
+
+    fn contains(&self, date: NaiveDate) -> bool {
+        self.start.is_some_and(|start| start <= date) && date <= self.end
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn percent(n: usize, of: usize) -> f64 {
+    if of == 0 {
+        0.0
+    } else {
+        n as f64 / of as f64 * 100.0
     }
 }
 
+fn ratio(n: usize, of: usize) -> String {
+    format!("{n}/{of} ({:.0}%)", percent(n, of))
+}
+
 fn find_files(md_files: &mut Vec<PathBuf>, dir: PathBuf) -> RibbitR<()> {
     for el in dir.read_dir()? {
         let path = el.as_ref().unwrap().path();
@@ -223,3 +884,92 @@ fn find_files(md_files: &mut Vec<PathBuf>, dir: PathBuf) -> RibbitR<()> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(yaml: &str) -> Result<Fm, serde_yaml::Error> {
+        serde_yaml::from_str(&format!("title: t\ndate: 2024-01-01\n{yaml}"))
+    }
+
+    fn habits(yaml: &str) -> Result<Habits, serde_yaml::Error> {
+        entry(yaml).map(|fm| fm.habits)
+    }
+
+    fn done(names: &[&str]) -> Habits {
+        names
+            .iter()
+            .map(|name| (name.to_string(), HabitValue::Bool(true)))
+            .collect()
+    }
+
+    #[test]
+    fn list_of_maps() {
+        let fm: Fm = serde_yaml::from_str(include_str!("../format.yml")).unwrap();
+        assert_eq!(fm.habits, done(&["workout", "code", "read"]));
+    }
+
+    #[test]
+    fn map() {
+        let habits = habits("habits:\n  read: 20m\n  run: false\n  walk: skip\n").unwrap();
+        assert_eq!(habits["read"], HabitValue::Duration(20.0));
+        assert_eq!(habits["run"], HabitValue::Bool(false));
+        assert_eq!(habits["walk"], HabitValue::Skip);
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(
+            habits("habits: [workout, read]\n").unwrap(),
+            done(&["workout", "read"])
+        );
+        assert_eq!(
+            habits("habits:\n  - workout\n  - read: 3\n").unwrap(),
+            Habits::from([
+                ("workout".to_owned(), HabitValue::Bool(true)),
+                ("read".to_owned(), HabitValue::Number(3.0)),
+            ])
+        );
+        assert_eq!(habits("habits: read\n").unwrap(), done(&["read"]));
+    }
+
+    #[test]
+    fn empty() {
+        assert!(habits("habits: ~\n").unwrap().is_empty());
+        assert!(habits("habits:\n").unwrap().is_empty());
+        assert!(habits("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn bad_value_keeps_its_location() {
+        let e = habits("habits:\n  - read: true\n  - x: maybe\n").unwrap_err();
+        let location = e.location().unwrap();
+        assert_eq!((location.line(), location.column()), (5, 8));
+        assert!(
+            e.to_string()
+                .starts_with("habits[1].x: `maybe` is not a boolean"),
+            "{e}"
+        );
+        let e = habits("habits:\n  - x: maybe\n").unwrap_err();
+        assert!(e.to_string().starts_with("habits[0].x: "), "{e}");
+    }
+
+    #[test]
+    fn folds_aliases() {
+        let config: Config =
+            toml::from_str("[habits.workout]\naliases = [\"gym\", \"exercise\"]").unwrap();
+        let mut fm = vec![
+            entry("habits: {gym: true, workout: false}").unwrap(),
+            entry("habits: [exercise, read, gym]").unwrap(),
+        ];
+        let hits = fold_aliases(&mut fm, &config);
+        assert_eq!(fm[0].habits, done(&["workout"]));
+        assert_eq!(fm[1].habits, done(&["workout", "read"]));
+        let hit = |alias: &str| (alias.to_owned(), "workout".to_owned());
+        assert_eq!(
+            hits,
+            AliasHits::from([(hit("exercise"), 1), (hit("gym"), 2)])
+        );
+    }
+}
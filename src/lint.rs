@@ -0,0 +1,187 @@
+use std::{
+    collections::BTreeMap,
+    fs::read_to_string,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::NaiveDate;
+use serde::Serialize;
+use serde_yaml::Value;
+
+use crate::{
+    config::{Config, Journal},
+    frontmatter::{extract, Diagnostic},
+    output::{emit, OutputFormat, Report},
+    value::parse_duration,
+    Fm, RibbitR,
+};
+
+#[derive(Debug, Serialize)]
+struct LintReport {
+    files: usize,
+    problems: Vec<Diagnostic>,
+}
+
+impl Report for LintReport {
+    type Row = Diagnostic;
+
+    fn rows(&self) -> Vec<Diagnostic> {
+        self.problems.clone()
+    }
+
+    fn table(&self, out: &mut dyn Write) -> io::Result<()> {
+        for diagnostic in &self.problems {
+            writeln!(out, "{diagnostic}")?;
+        }
+        writeln!(
+            out,
+            "{} files checked, {} problems",
+            self.files,
+            self.problems.len()
+        )?;
+        Ok(())
+    }
+}
+
+// Dates only need to be unique within a journal.
+pub(crate) fn lint(
+    journals: &[(&Journal, Vec<PathBuf>)],
+    config: &Config,
+    format: OutputFormat,
+) -> RibbitR<()> {
+    let mut diagnostics = Vec::new();
+    let mut dates: BTreeMap<(&str, NaiveDate), Vec<&PathBuf>> = BTreeMap::new();
+    for (journal, md_files) in journals {
+        for path in md_files {
+            if let Some(date) = lint_file(path, config, &mut diagnostics) {
+                dates.entry((&journal.name, date)).or_default().push(path);
+            }
+        }
+    }
+    for ((_, date), paths) in dates.into_iter().filter(|(_, paths)| paths.len() > 1) {
+        for path in &paths {
+            let others: Vec<String> = paths
+                .iter()
+                .filter(|other| other != &path)
+                .map(|other| other.display().to_string())
+                .collect();
+            let reason = format!("duplicate date {date}, also in {}", others.join(", "));
+            diagnostics.push(Diagnostic::new(path, None, reason));
+        }
+    }
+
+    let report = LintReport {
+        files: journals.iter().map(|(_, files)| files.len()).sum(),
+        problems: diagnostics,
+    };
+    emit(&report, format)?;
+    if report.problems.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("lint found {} problems", report.problems.len()).into())
+    }
+}
+
+fn lint_file(path: &Path, config: &Config, diagnostics: &mut Vec<Diagnostic>) -> Option<NaiveDate> {
+    let file = read_to_string(path)
+        .map_err(|e| diagnostics.push(Diagnostic::new(path, None, e)))
+        .ok()?;
+    let block = extract(&file)
+        .map_err(|e| diagnostics.push(Diagnostic::new(path, None, e)))
+        .ok()?;
+    let value: Value = block
+        .parse(path)
+        .map_err(|diagnostic| diagnostics.push(diagnostic))
+        .ok()?;
+
+    let found = diagnostics.len();
+    let mut problem = |needle: &str, reason: String| {
+        // Flow-style habits share a line, so fall back to the `habits` key.
+        let location = block
+            .line_of(needle)
+            .or_else(|| block.line_of("habits"))
+            .map(|line| (line, 1));
+        diagnostics.push(Diagnostic::new(path, location, reason));
+    };
+
+    if let Some(habits) = value.get("habits") {
+        lint_habits(habits, config, &mut problem);
+    }
+    let date = match value.get("date") {
+        None => {
+            problem("", "missing `date`".to_owned());
+            None
+        }
+        Some(date) => {
+            let parsed = date.as_str().and_then(|d| d.parse::<NaiveDate>().ok());
+            if parsed.is_none() {
+                problem("date", "`date` is not a valid YYYY-MM-DD date".to_owned());
+            }
+            parsed
+        }
+    };
+    if let (Some(date), Some(named)) = (date, filename_date(path)) {
+        if date != named {
+            problem(
+                "date",
+                format!("date {date} disagrees with filename date {named}"),
+            );
+        }
+    }
+
+    // Anything the checks above missed still keeps the entry out of reports.
+    if diagnostics.len() == found {
+        if let Err(diagnostic) = block.parse::<Fm>(path) {
+            diagnostics.push(diagnostic);
+        }
+    }
+    date
+}
+
+fn lint_habits(habits: &Value, config: &Config, problem: &mut impl FnMut(&str, String)) {
+    let aliases = config.aliases();
+    let entries: Vec<(&Value, Option<&Value>)> = match habits {
+        Value::Null => Vec::new(),
+        Value::Mapping(map) => map.iter().map(|(k, v)| (k, Some(v))).collect(),
+        Value::Sequence(list) => list
+            .iter()
+            .flat_map(|item| match item {
+                Value::Mapping(map) => map.iter().map(|(k, v)| (k, Some(v))).collect(),
+                item => vec![(item, None)],
+            })
+            .collect(),
+        _ => {
+            problem("habits", "`habits` must be a map or a list".to_owned());
+            return;
+        }
+    };
+
+    for (key, value) in entries {
+        let Some(name) = key.as_str() else {
+            problem("habits", format!("habit name {key:?} is not a string"));
+            continue;
+        };
+        let text = |s: &str| s.trim().eq_ignore_ascii_case("skip") || parse_duration(s).is_some();
+        let amount = |v: &Value| v.is_number() || v.as_str().is_some_and(text);
+        if value.is_some_and(|v| !v.is_bool() && !amount(v)) {
+            problem(
+                name,
+                format!("habit `{name}` is not a boolean, number, duration or `skip`"),
+            );
+        }
+        let known = config.habits.contains_key(name) || aliases.contains_key(name);
+        if !config.habits.is_empty() && !known {
+            problem(name, format!("unknown habit `{name}`"));
+        }
+    }
+}
+
+// The first YYYY-MM-DD found in the file name, if any.
+fn filename_date(path: &Path) -> Option<NaiveDate> {
+    let stem = path.file_stem()?.to_str()?;
+    (0..stem.len()).find_map(|i| {
+        stem.get(i..i + 10)
+            .and_then(|s| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok())
+    })
+}
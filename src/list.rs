@@ -0,0 +1,157 @@
+use std::{
+    collections::BTreeSet,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use chrono::NaiveDate;
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::{output::Report, value::HabitValue, Fm, Habits};
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub(crate) enum ListSort {
+    #[default]
+    Date,
+    Title,
+    Path,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct ListEntry {
+    date: NaiveDate,
+    journal: String,
+    title: String,
+    path: PathBuf,
+    habits: Habits,
+}
+
+// CSV has no room for a map, so the done habits are joined into one column,
+// amounts as `habit=amount`.
+#[derive(Debug, Serialize)]
+pub(crate) struct ListRow {
+    date: NaiveDate,
+    journal: String,
+    title: String,
+    path: PathBuf,
+    done: String,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct ListReport {
+    habits: Vec<String>,
+    entries: Vec<ListEntry>,
+}
+
+pub(crate) fn list(
+    fm: Vec<Fm>,
+    names: &BTreeSet<String>,
+    sort: ListSort,
+    reverse: bool,
+) -> ListReport {
+    let mut entries: Vec<ListEntry> = fm
+        .into_iter()
+        .map(|f| ListEntry {
+            date: f.date,
+            journal: f.journal,
+            title: f.title,
+            path: f.path,
+            habits: f.habits,
+        })
+        .collect();
+    match sort {
+        ListSort::Date => entries.sort_by_key(|e| e.date),
+        ListSort::Title => entries.sort_by(|a, b| a.title.cmp(&b.title)),
+        ListSort::Path => entries.sort_by(|a, b| a.path.cmp(&b.path)),
+    }
+    if reverse {
+        entries.reverse();
+    }
+    ListReport {
+        habits: names.iter().cloned().collect(),
+        entries,
+    }
+}
+
+impl Report for ListReport {
+    type Row = ListRow;
+
+    fn rows(&self) -> Vec<ListRow> {
+        self.entries
+            .iter()
+            .map(|e| ListRow {
+                date: e.date,
+                journal: e.journal.clone(),
+                title: e.title.clone(),
+                path: e.path.clone(),
+                done: done(&e.habits).join(" "),
+            })
+            .collect()
+    }
+
+    // One line per entry: ✓ done, ✗ recorded as not done, · not recorded, or
+    // the amount recorded.
+    // The journal column only appears when entries come from several.
+    fn table(&self, out: &mut dyn Write) -> io::Result<()> {
+        let width =
+            |field: fn(&ListEntry) -> usize| self.entries.iter().map(field).max().unwrap_or(0);
+        let title_width = width(|e| e.title.len());
+        let journals: BTreeSet<&str> = self.entries.iter().map(|e| e.journal.as_str()).collect();
+        let journal_width = (journals.len() > 1).then(|| width(|e| e.journal.len()).max(7));
+        write!(out, "{:10}", "date")?;
+        if let Some(width) = journal_width {
+            write!(out, "  {:width$}", "journal")?;
+        }
+        // Amounts are shown as recorded, so a column may be wider than its name.
+        let cells: Vec<usize> = self
+            .habits
+            .iter()
+            .map(|habit| {
+                let amounts = self.entries.iter().filter_map(|e| e.habits.get(habit));
+                amounts
+                    .map(|value| cell(Some(*value)).chars().count())
+                    .chain([habit.len()])
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        for (habit, width) in self.habits.iter().zip(&cells) {
+            write!(out, "  {habit:width$}")?;
+        }
+        writeln!(out, "  {:title_width$}  path", "title")?;
+        for e in &self.entries {
+            write!(out, "{}", e.date)?;
+            if let Some(width) = journal_width {
+                write!(out, "  {:width$}", e.journal)?;
+            }
+            for (habit, width) in self.habits.iter().zip(&cells) {
+                let mark = cell(e.habits.get(habit).copied());
+                write!(out, "  {mark:^width$}")?;
+            }
+            writeln!(out, "  {:title_width$}  {}", e.title, e.path.display())?;
+        }
+        Ok(())
+    }
+}
+
+fn cell(value: Option<HabitValue>) -> String {
+    match value {
+        Some(HabitValue::Bool(true)) => "✓".to_owned(),
+        Some(HabitValue::Bool(false)) => "✗".to_owned(),
+        Some(HabitValue::Skip) => "-".to_owned(),
+        Some(value) => value.to_string(),
+        None => "·".to_owned(),
+    }
+}
+
+fn done(habits: &Habits) -> Vec<String> {
+    habits
+        .iter()
+        .filter(|(_, value)| value.done())
+        .map(|(habit, value)| match value {
+            HabitValue::Bool(_) => habit.clone(),
+            value => format!("{habit}={value}"),
+        })
+        .collect()
+}
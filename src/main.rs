@@ -1,4 +1,7 @@
 #![warn(clippy::pedantic, clippy::style, clippy::nursery)]
 fn main() {
-    ribbit::run().unwrap();
+    if let Err(e) = ribbit::run() {
+        eprintln!("error: {e}");
+        std::process::exit(1);
+    }
 }
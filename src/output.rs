@@ -0,0 +1,56 @@
+use std::io::{self, Write};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::RibbitR;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+    Csv,
+    Yaml,
+}
+
+// A report printed as a table for people or serialised for other tools. JSON
+// and YAML get the whole report; CSV gets one record per row.
+pub(crate) trait Report: Serialize {
+    type Row: Serialize;
+
+    fn rows(&self) -> Vec<Self::Row>;
+    fn table(&self, out: &mut dyn Write) -> io::Result<()>;
+}
+
+// A reader that goes away early, like `head`, has seen all it wanted, so a
+// closed pipe ends the output quietly.
+pub(crate) fn emit<R: Report>(report: &R, format: OutputFormat) -> RibbitR<()> {
+    let mut out = io::stdout().lock();
+    let written = match format {
+        OutputFormat::Table => report.table(&mut out),
+        OutputFormat::Json => writeln!(out, "{}", serde_json::to_string_pretty(report)?),
+        OutputFormat::Yaml => write!(out, "{}", serde_yaml::to_string(report)?),
+        OutputFormat::Csv => csv(report, &mut out),
+    };
+    match written.and_then(|()| out.flush()) {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        written => Ok(written?),
+    }
+}
+
+fn csv<R: Report>(report: &R, out: &mut dyn Write) -> io::Result<()> {
+    let mut writer = csv::Writer::from_writer(out);
+    for row in report.rows() {
+        // Keep the kind of a write error so a closed pipe is still seen as one.
+        writer.serialize(row).map_err(|e| {
+            let kind = match e.kind() {
+                csv::ErrorKind::Io(e) => e.kind(),
+                _ => io::ErrorKind::InvalidData,
+            };
+            io::Error::new(kind, e)
+        })?;
+    }
+    writer.flush()
+}
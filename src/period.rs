@@ -0,0 +1,90 @@
+use chrono::{Datelike, Days, Duration, Months, NaiveDate, Weekday};
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::range::DateRange;
+
+// Calendar periods. Every period is anchored to its year, so this month in
+// 2023 never matches the same month of an earlier year.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Period {
+    #[value(alias("d"))]
+    Day,
+    #[value(alias("w"))]
+    Week,
+    #[value(alias("m"))]
+    Month,
+    #[value(alias("q"))]
+    Quarter,
+    #[value(alias("y"))]
+    Year,
+}
+
+impl Period {
+    // First day of the period containing `date`. Weeks begin on `week_start`.
+    pub(crate) fn start(self, date: NaiveDate, week_start: Weekday) -> NaiveDate {
+        let first_of = |month| NaiveDate::from_ymd_opt(date.year(), month, 1);
+        let start = match self {
+            Self::Day => Some(date),
+            Self::Week => Some(date - Duration::days(i64::from(days_into_week(date, week_start)))),
+            Self::Month => first_of(date.month()),
+            Self::Quarter => first_of(date.month0() / 3 * 3 + 1),
+            Self::Year => first_of(1),
+        };
+        start.expect("the first day of a period is a valid date")
+    }
+
+    // Moves the start of a period `n` periods forward, or back if negative,
+    // stopping at the first or last date chrono supports.
+    pub(crate) fn advance(self, start: NaiveDate, n: i32) -> NaiveDate {
+        let days = |per: u64| {
+            let days = Days::new(u64::from(n.unsigned_abs()) * per);
+            if n < 0 {
+                start.checked_sub_days(days)
+            } else {
+                start.checked_add_days(days)
+            }
+        };
+        let months = |per: u32| {
+            let months = Months::new(n.unsigned_abs().checked_mul(per)?);
+            if n < 0 {
+                start.checked_sub_months(months)
+            } else {
+                start.checked_add_months(months)
+            }
+        };
+        match self {
+            Self::Day => days(1),
+            Self::Week => days(7),
+            Self::Month => months(1),
+            Self::Quarter => months(3),
+            Self::Year => months(12),
+        }
+        .unwrap_or(if n < 0 {
+            NaiveDate::MIN
+        } else {
+            NaiveDate::MAX
+        })
+    }
+
+    pub(crate) fn containing(self, date: NaiveDate, week_start: Weekday) -> DateRange {
+        let start = self.start(date, week_start);
+        let end = self.advance(start, 1) - Duration::days(1);
+        DateRange::new(start, end)
+    }
+
+    // The period `ago` periods before the one containing `today`.
+    pub(crate) fn ago(self, today: NaiveDate, ago: u32, week_start: Weekday) -> DateRange {
+        let ago = i32::try_from(ago).unwrap_or(i32::MAX);
+        self.containing(
+            self.advance(self.start(today, week_start), -ago),
+            week_start,
+        )
+    }
+}
+
+// How many days `date` is past the most recent `week_start`.
+pub(crate) fn days_into_week(date: NaiveDate, week_start: Weekday) -> u32 {
+    (date.weekday().num_days_from_monday() + 7 - week_start.num_days_from_monday()) % 7
+}
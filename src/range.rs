@@ -0,0 +1,300 @@
+use chrono::{Datelike, Days, Duration, NaiveDate, Weekday};
+
+use crate::{filter_by_time, period::Period, today, window, Fm};
+
+// An inclusive span of days; a missing bound is open-ended.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct DateRange {
+    pub(crate) start: Option<NaiveDate>,
+    pub(crate) end: Option<NaiveDate>,
+}
+
+impl DateRange {
+    pub(crate) const fn new(start: NaiveDate, end: NaiveDate) -> Self {
+        Self {
+            start: Some(start),
+            end: Some(end),
+        }
+    }
+
+    pub(crate) fn contains(&self, date: NaiveDate) -> bool {
+        self.start.is_none_or(|start| start <= date) && self.end.is_none_or(|end| date <= end)
+    }
+}
+
+// A day given either absolutely or relative to today.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum DateExpr {
+    Date(NaiveDate),
+    DaysAgo(i64),
+    Last(Weekday),
+}
+
+impl DateExpr {
+    pub(crate) fn resolve(self, today: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Date(date) => date,
+            Self::DaysAgo(days) => back(today, days),
+            Self::Last(weekday) => {
+                let back = (today.weekday().num_days_from_monday() + 7
+                    - weekday.num_days_from_monday()
+                    - 1)
+                    % 7
+                    + 1;
+                today - Duration::days(i64::from(back))
+            }
+        }
+    }
+}
+
+// `days` before `date`, stopping at the earliest date chrono supports.
+pub(crate) fn back(date: NaiveDate, days: i64) -> NaiveDate {
+    let span = span(days);
+    if days < 0 {
+        span.and_then(|span| date.checked_add_days(span))
+            .unwrap_or(NaiveDate::MAX)
+    } else {
+        span.and_then(|span| date.checked_sub_days(span))
+            .unwrap_or(NaiveDate::MIN)
+    }
+}
+
+// chrono dates span about 262,000 years either side of year 0, and its own
+// checks overflow on spans much longer than that.
+fn span(days: i64) -> Option<Days> {
+    const LONGEST: u64 = 2 * 262_144 * 366;
+    Some(days.unsigned_abs())
+        .filter(|&days| days <= LONGEST)
+        .map(Days::new)
+}
+
+// Accepts `YYYY-MM-DD`, `today`, `yesterday`, `N days ago`, `Nd ago` or just
+// `Nd`, `last <weekday>` and a bare weekday (meaning the most recent one). A
+// bare number is refused, since `2026` reads like a year rather than days.
+pub(crate) fn parse_date_expr(s: &str) -> Result<DateExpr, String> {
+    let s = s.trim().to_lowercase();
+    if let Ok(date) = s.parse::<NaiveDate>() {
+        return Ok(DateExpr::Date(date));
+    }
+    match s.as_str() {
+        "today" => return Ok(DateExpr::DaysAgo(0)),
+        "yesterday" => return Ok(DateExpr::DaysAgo(1)),
+        _ => {}
+    }
+    if let Some(ago) = s.strip_suffix(" ago") {
+        return parse_days(ago).map(DateExpr::DaysAgo);
+    }
+    if s.starts_with(|c: char| c.is_ascii_digit()) {
+        if s.chars().all(|c| c.is_ascii_digit()) {
+            return Err(format!(
+                "`{s}` needs a unit, e.g. `{s}d`, or use --period {s} for a year"
+            ));
+        }
+        return parse_days(&s).map(DateExpr::DaysAgo);
+    }
+    let weekday = s.strip_prefix("last ").unwrap_or(&s);
+    weekday
+        .parse::<Weekday>()
+        .map(DateExpr::Last)
+        .map_err(|_| format!("`{s}` is not a date, weekday or `N days ago`"))
+}
+
+// Accepts a number of days, weeks, months or years: `30d`, `2w`, `3m`, `1y`,
+// `10 days`. Months and years are taken as 30 and 365 days. Spans reaching
+// back past the earliest date chrono supports are refused.
+pub(crate) fn parse_days(s: &str) -> Result<i64, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (n, unit) = s.split_at(split);
+    if n.is_empty() {
+        return Err(format!("`{s}` does not start with a number"));
+    }
+    let too_far = || format!("`{s}` reaches too far back");
+    let n: i64 = n.parse().map_err(|_| too_far())?;
+    let per = match unit.trim() {
+        "" | "d" | "day" | "days" => 1,
+        "w" | "week" | "weeks" => 7,
+        "m" | "month" | "months" => 30,
+        "y" | "year" | "years" => 365,
+        unit => return Err(format!("unknown unit `{unit}`, expected d, w, m or y")),
+    };
+    let days = n.checked_mul(per).ok_or_else(too_far)?;
+    span(days)
+        .and_then(|span| today().checked_sub_days(span))
+        .ok_or_else(too_far)?;
+    Ok(days)
+}
+
+// Accepts `YYYY`, `YYYY-Qn`, `YYYY-MM`, `YYYY-Www` and `YYYY-MM-DD`.
+pub(crate) fn parse_period(s: &str) -> Result<DateRange, String> {
+    let invalid = || format!("`{s}` is not a year, quarter, month, ISO week or date");
+    let mut parts = s.trim().splitn(2, '-');
+    let year: i32 = parts
+        .next()
+        .and_then(|y| y.parse().ok())
+        .ok_or_else(invalid)?;
+    let (period, start) = match parts.next() {
+        None => (Period::Year, NaiveDate::from_ymd_opt(year, 1, 1)),
+        Some(rest) if rest.starts_with(['Q', 'q']) => {
+            let quarter = rest[1..]
+                .parse::<u32>()
+                .ok()
+                .filter(|q| (1..=4).contains(q));
+            let start = quarter.and_then(|q| NaiveDate::from_ymd_opt(year, q * 3 - 2, 1));
+            (Period::Quarter, start)
+        }
+        Some(rest) if rest.starts_with(['W', 'w']) => {
+            let week = rest[1..].parse().ok();
+            let start = week.and_then(|w| NaiveDate::from_isoywd_opt(year, w, Weekday::Mon));
+            (Period::Week, start)
+        }
+        Some(rest) if rest.len() <= 2 => {
+            let month = rest.parse().ok();
+            let start = month.and_then(|m| NaiveDate::from_ymd_opt(year, m, 1));
+            (Period::Month, start)
+        }
+        Some(_) => (Period::Day, s.trim().parse().ok()),
+    };
+    start
+        .map(|start| period.containing(start, Weekday::Mon))
+        .ok_or_else(invalid)
+}
+
+// Far enough back for any journal while staying well inside chrono's dates.
+const MAX_AGO: i64 = 100_000;
+
+#[derive(clap::Args, Debug, Clone, Default)]
+pub(crate) struct RangeArgs {
+    /// The current day, week, month, quarter or year
+    #[arg(short, long, value_enum, conflicts_with_all = ["since", "until", "last", "period"])]
+    time: Option<Period>,
+
+    /// Go this many periods back from the current --time period
+    #[arg(long, requires = "time", default_value_t = 0, value_parser = clap::value_parser!(u32).range(..=MAX_AGO))]
+    ago: u32,
+
+    /// First day to include: a date, `yesterday`, `last monday`, `10 days ago`...
+    #[arg(long, value_parser = parse_date_expr)]
+    since: Option<DateExpr>,
+
+    /// Last day to include, in the same forms as --since
+    #[arg(long, value_parser = parse_date_expr)]
+    until: Option<DateExpr>,
+
+    /// The trailing span of days ending today (or --at), e.g. `30d`, `2w`, `6m`
+    #[arg(long, alias = "rolling", value_parser = parse_days, conflicts_with_all = ["since", "until", "period"])]
+    last: Option<i64>,
+
+    /// Day the --last window ends on
+    #[arg(long, value_parser = parse_date_expr, requires = "last")]
+    at: Option<DateExpr>,
+
+    /// Only the most recent N entries in the range, however far apart
+    #[arg(long)]
+    entries: Option<usize>,
+
+    /// A whole year, quarter, month, ISO week or day: `2022`, `2022-Q3`, `2022-07`, `2022-W14`
+    #[arg(long, value_parser = parse_period, conflicts_with_all = ["since", "until"])]
+    period: Option<DateRange>,
+}
+
+impl RangeArgs {
+    pub(crate) fn range(&self, today: NaiveDate, week_start: Weekday) -> DateRange {
+        if let Some(time) = self.time {
+            return time.ago(today, self.ago, week_start);
+        }
+        if let Some(days) = self.last {
+            let end = self.at.map_or(today, |at| at.resolve(today));
+            return window::rolling(days, end);
+        }
+        if let Some(period) = self.period {
+            return period;
+        }
+        DateRange {
+            start: self.since.map(|since| since.resolve(today)),
+            end: self.until.map(|until| until.resolve(today)),
+        }
+    }
+
+    pub(crate) fn select(&self, fm: Vec<Fm>, today: NaiveDate, week_start: Weekday) -> Vec<Fm> {
+        let fm = filter_by_time(fm, self.range(today, week_start));
+        match self.entries {
+            Some(n) => window::last_entries(fm, n),
+            None => fm,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    fn resolve(s: &str) -> NaiveDate {
+        // A Wednesday.
+        parse_date_expr(s).unwrap().resolve(date("2026-10-14"))
+    }
+
+    #[test]
+    fn date_exprs() {
+        assert_eq!(resolve("2024-01-05"), date("2024-01-05"));
+        assert_eq!(resolve("today"), date("2026-10-14"));
+        assert_eq!(resolve("Yesterday"), date("2026-10-13"));
+        assert_eq!(resolve("3 days ago"), date("2026-10-11"));
+        assert_eq!(resolve("2w ago"), date("2026-09-30"));
+        assert_eq!(resolve("10d"), date("2026-10-04"));
+        assert_eq!(resolve("last monday"), date("2026-10-12"));
+        assert_eq!(resolve("wed"), date("2026-10-07"));
+    }
+
+    #[test]
+    fn bad_date_exprs() {
+        for s in ["2026", "10 parsecs", "someday", "99999999999y"] {
+            assert!(parse_date_expr(s).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn days() {
+        assert_eq!(parse_days("30d"), Ok(30));
+        assert_eq!(parse_days("2 weeks"), Ok(14));
+        assert_eq!(parse_days("3m"), Ok(90));
+        assert_eq!(parse_days("1y"), Ok(365));
+        assert!(parse_days("d").is_err());
+        assert!(parse_days("9223372036854775807w").is_err());
+    }
+
+    #[test]
+    fn periods() {
+        let range = |start, end| DateRange::new(date(start), date(end));
+        assert_eq!(parse_period("2022"), Ok(range("2022-01-01", "2022-12-31")));
+        assert_eq!(
+            parse_period("2022-Q3"),
+            Ok(range("2022-07-01", "2022-09-30"))
+        );
+        assert_eq!(
+            parse_period("2022-02"),
+            Ok(range("2022-02-01", "2022-02-28"))
+        );
+        assert_eq!(
+            parse_period("2022-W14"),
+            Ok(range("2022-04-04", "2022-04-10"))
+        );
+        assert_eq!(
+            parse_period("2022-07-04"),
+            Ok(range("2022-07-04", "2022-07-04"))
+        );
+        for s in ["22x", "2022-Q5", "2022-13", "2022-W60", "2022-02-30"] {
+            assert!(parse_period(s).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn back_saturates() {
+        assert_eq!(back(date("2026-10-14"), i64::MAX), NaiveDate::MIN);
+        assert_eq!(back(date("2026-10-14"), i64::MIN), NaiveDate::MAX);
+    }
+}
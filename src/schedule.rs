@@ -0,0 +1,250 @@
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use serde::Deserialize;
+
+use crate::{skip::Skips, value::Polarity};
+
+// The days a habit is expected on. Doing it on any other day is a bonus that
+// neither raises nor lowers its completion rate.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub(crate) enum Schedule {
+    #[default]
+    Daily,
+    On(Vec<Weekday>),
+    // Every `days` days counted from `from`.
+    Every {
+        days: u32,
+        from: NaiveDate,
+    },
+}
+
+pub(crate) static DAILY: Schedule = Schedule::Daily;
+
+const WEEKDAYS: [Weekday; 5] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+];
+
+impl TryFrom<String> for Schedule {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, String> {
+        parse_schedule(&s)
+    }
+}
+
+// Accepts `daily`, `weekdays`, `weekends`, `mon,wed,fri` and `every 3 days`,
+// optionally followed by `from 2024-01-01` to pick which days those are.
+// Without one, the count starts at the Unix epoch.
+pub(crate) fn parse_schedule(s: &str) -> Result<Schedule, String> {
+    let s = s.trim().to_lowercase();
+    match s.as_str() {
+        "daily" | "every day" => return Ok(Schedule::Daily),
+        "weekdays" | "weekends" => return parse_weekdays(&s).map(Schedule::On),
+        _ => {}
+    }
+    let Some(every) = s.strip_prefix("every ") else {
+        return parse_weekdays(&s).map(Schedule::On);
+    };
+    let (every, from) = match every.split_once(" from ") {
+        Some((every, from)) => {
+            let from = from
+                .trim()
+                .parse()
+                .map_err(|_| format!("`{from}` is not a date like 2024-01-01"))?;
+            (every, from)
+        }
+        None => (every, NaiveDate::default()),
+    };
+    let days = every
+        .trim()
+        .strip_suffix(" days")
+        .and_then(|n| n.trim().parse().ok())
+        .filter(|&n| n > 0)
+        .ok_or_else(|| format!("schedule `{s}` should look like `every 3 days`"))?;
+    Ok(Schedule::Every { days, from })
+}
+
+// `weekdays`, `weekends` or a comma separated list like `mon,wed,fri`.
+pub(crate) fn parse_weekdays(s: &str) -> Result<Vec<Weekday>, String> {
+    let days = match s.trim() {
+        "weekdays" => WEEKDAYS.to_vec(),
+        "weekends" => vec![Weekday::Sat, Weekday::Sun],
+        days => days
+            .split(',')
+            .map(|day| {
+                day.trim()
+                    .parse()
+                    .map_err(|_| format!("`{}` is not a weekday", day.trim()))
+            })
+            .collect::<Result<_, _>>()?,
+    };
+    Ok(days)
+}
+
+impl Schedule {
+    pub(crate) fn due(&self, date: NaiveDate) -> bool {
+        match self {
+            Self::Daily => true,
+            Self::On(days) => days.contains(&date.weekday()),
+            Self::Every { days, from } => {
+                (date - *from).num_days().rem_euclid(i64::from(*days)) == 0
+            }
+        }
+    }
+}
+
+// The days one habit counts on: scheduled, and not declared a skip day, and
+// whether doing it on one of them is what counts.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct HabitDays<'a> {
+    pub(crate) habit: &'a str,
+    pub(crate) schedule: &'a Schedule,
+    pub(crate) skips: &'a Skips,
+    pub(crate) polarity: Polarity,
+}
+
+impl HabitDays<'_> {
+    pub(crate) fn avoid(&self) -> bool {
+        self.polarity == Polarity::Avoid
+    }
+
+    pub(crate) const fn kept(&self, done: bool) -> bool {
+        self.polarity.kept(done)
+    }
+
+    pub(crate) fn scheduled(&self, date: NaiveDate) -> bool {
+        self.schedule.due(date)
+    }
+
+    // A scheduled day that was skipped.
+    pub(crate) fn skipped(&self, date: NaiveDate) -> bool {
+        self.scheduled(date) && self.skips.skipped(self.habit, date)
+    }
+
+    pub(crate) fn due(&self, date: NaiveDate) -> bool {
+        self.scheduled(date) && !self.skips.skipped(self.habit, date)
+    }
+
+    // The closest due day before `date`. Schedules repeat within a week or
+    // their own interval, so only a skip that never ends could stop the search
+    // finding one; it gives up after a few years of them.
+    pub(crate) fn due_before(&self, date: NaiveDate) -> NaiveDate {
+        (1..=SEARCH)
+            .map(|n| date - Duration::days(n))
+            .find(|&d| self.due(d))
+            .unwrap_or(date - Duration::days(SEARCH))
+    }
+
+    pub(crate) fn due_after(&self, date: NaiveDate) -> NaiveDate {
+        (1..=SEARCH)
+            .map(|n| date + Duration::days(n))
+            .find(|&d| self.due(d))
+            .unwrap_or(date + Duration::days(SEARCH))
+    }
+
+    // How many days from `start` to `end`, inclusive, are due.
+    pub(crate) fn due_days(&self, start: NaiveDate, end: NaiveDate) -> usize {
+        days(start, end).filter(|&d| self.due(d)).count()
+    }
+
+    pub(crate) fn skipped_days(&self, start: NaiveDate, end: NaiveDate) -> usize {
+        days(start, end).filter(|&d| self.skipped(d)).count()
+    }
+}
+
+const SEARCH: i64 = 3660;
+
+fn days(start: NaiveDate, end: NaiveDate) -> impl Iterator<Item = NaiveDate> {
+    start.iter_days().take_while(move |&d| d <= end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn schedules() {
+        assert_eq!(parse_schedule("Daily"), Ok(Schedule::Daily));
+        assert_eq!(
+            parse_schedule("weekdays"),
+            Ok(Schedule::On(WEEKDAYS.to_vec()))
+        );
+        assert_eq!(
+            parse_schedule("weekends"),
+            Ok(Schedule::On(vec![Weekday::Sat, Weekday::Sun]))
+        );
+        assert_eq!(
+            parse_schedule("mon, wed,fri"),
+            Ok(Schedule::On(vec![Weekday::Mon, Weekday::Wed, Weekday::Fri]))
+        );
+        assert_eq!(
+            parse_schedule("every 3 days"),
+            Ok(Schedule::Every {
+                days: 3,
+                from: NaiveDate::default()
+            })
+        );
+        assert_eq!(
+            parse_schedule("every 2 days from 2024-01-01"),
+            Ok(Schedule::Every {
+                days: 2,
+                from: date("2024-01-01")
+            })
+        );
+    }
+
+    #[test]
+    fn bad_schedules() {
+        for s in [
+            "sometimes",
+            "mon,funday",
+            "every 0 days",
+            "every day or two",
+            "every 3 days from monday",
+        ] {
+            assert!(parse_schedule(s).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn due() {
+        // 2024-01-01 is a Monday.
+        let on = parse_schedule("mon,wed").unwrap();
+        assert!(on.due(date("2024-01-01")));
+        assert!(!on.due(date("2024-01-02")));
+        assert!(on.due(date("2024-01-03")));
+
+        let every = parse_schedule("every 3 days from 2024-01-10").unwrap();
+        let due: Vec<u32> = date("2024-01-01")
+            .iter_days()
+            .take(20)
+            .filter(|&d| every.due(d))
+            .map(|d| d.day())
+            .collect();
+        // Days before the anchor keep the same rhythm.
+        assert_eq!(due, [1, 4, 7, 10, 13, 16, 19]);
+    }
+
+    #[test]
+    fn due_days() {
+        let schedule = parse_schedule("weekdays").unwrap();
+        let skips = Skips::default();
+        let days = HabitDays {
+            habit: "read",
+            schedule: &schedule,
+            skips: &skips,
+            polarity: Polarity::Build,
+        };
+        assert_eq!(days.due_days(date("2024-01-01"), date("2024-01-14")), 10);
+        assert_eq!(days.due_before(date("2024-01-08")), date("2024-01-05"));
+        assert_eq!(days.due_after(date("2024-01-05")), date("2024-01-08"));
+    }
+}
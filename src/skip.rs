@@ -0,0 +1,204 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Deserializer};
+
+use crate::{config::Config, value::HabitValue, Fm};
+
+// A run of days declared in the config, such as a holiday, that does not count
+// for some or all habits.
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct SkipRange {
+    #[serde(deserialize_with = "deserialize_date")]
+    from: NaiveDate,
+    #[serde(default, deserialize_with = "deserialize_end")]
+    to: Option<NaiveDate>,
+    #[serde(default)]
+    reason: Option<String>,
+    // Every habit when empty.
+    #[serde(default)]
+    habits: Vec<String>,
+}
+
+// TOML has its own date type, but a quoted date reads just as well.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawDate {
+    Date(toml::value::Datetime),
+    Text(String),
+}
+
+fn deserialize_date<'de, D: Deserializer<'de>>(d: D) -> Result<NaiveDate, D::Error> {
+    let date = match RawDate::deserialize(d)? {
+        RawDate::Date(date) => date.to_string(),
+        RawDate::Text(date) => date,
+    };
+    date.parse()
+        .map_err(|_| serde::de::Error::custom(format!("`{date}` is not a YYYY-MM-DD date")))
+}
+
+fn deserialize_end<'de, D: Deserializer<'de>>(d: D) -> Result<Option<NaiveDate>, D::Error> {
+    deserialize_date(d).map(Some)
+}
+
+// An entry's `skip` key: `true`, a reason such as `sick`, or a list of them.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawSkip {
+    Bool(bool),
+    Reason(String),
+    Reasons(Vec<String>),
+}
+
+pub(crate) fn deserialize_skip<'de, D: Deserializer<'de>>(
+    d: D,
+) -> Result<Option<Vec<String>>, D::Error> {
+    Ok(match Option::<RawSkip>::deserialize(d)? {
+        None | Some(RawSkip::Bool(false)) => None,
+        Some(RawSkip::Bool(true)) => Some(Vec::new()),
+        Some(RawSkip::Reason(reason)) => Some(vec![reason]),
+        Some(RawSkip::Reasons(reasons)) => Some(reasons),
+    })
+}
+
+// Who a skip day applies to: every habit, or only the ones named.
+#[derive(Debug, Clone, Default)]
+struct SkipDay {
+    all: bool,
+    habits: BTreeSet<String>,
+}
+
+// Every skip day, whether declared in an entry, as `habit: skip`, or as a
+// range in the config.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Skips(BTreeMap<NaiveDate, SkipDay>);
+
+impl Skips {
+    pub(crate) fn new(fm: &[Fm], config: &Config) -> Self {
+        let mut days: BTreeMap<NaiveDate, SkipDay> = BTreeMap::new();
+        for f in fm {
+            let day = days.entry(f.date).or_default();
+            day.all |= f.skip.is_some();
+            day.habits.extend(
+                f.habits
+                    .iter()
+                    .filter(|(_, value)| **value == HabitValue::Skip)
+                    .map(|(habit, _)| habit.clone()),
+            );
+        }
+        for range in &config.skip {
+            let end = range.to.unwrap_or(range.from);
+            for date in range.from.iter_days().take_while(|&d| d <= end) {
+                let day = days.entry(date).or_default();
+                day.all |= range.habits.is_empty();
+                day.habits.extend(
+                    range
+                        .habits
+                        .iter()
+                        .map(|habit| config.canonical(habit).to_owned()),
+                );
+            }
+        }
+        days.retain(|_, day| day.all || !day.habits.is_empty());
+        Self(days)
+    }
+
+    pub(crate) fn skipped(&self, habit: &str, date: NaiveDate) -> bool {
+        self.0
+            .get(&date)
+            .is_some_and(|day| day.all || day.habits.contains(habit))
+    }
+}
+
+impl SkipRange {
+    pub(crate) fn describe(&self) -> String {
+        let mut out = match self.to {
+            Some(to) if to != self.from => format!("{} to {to}", self.from),
+            _ => self.from.to_string(),
+        };
+        if !self.habits.is_empty() {
+            out.push_str(&format!(" for {}", self.habits.join(", ")));
+        }
+        if let Some(reason) = &self.reason {
+            out.push_str(&format!(" ({reason})"));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    fn entry(date: &str, rest: &str) -> Fm {
+        serde_yaml::from_str(&format!("title: t\ndate: {date}\n{rest}")).unwrap()
+    }
+
+    #[test]
+    fn skip_key() {
+        assert_eq!(entry("2024-01-01", "").skip, None);
+        assert_eq!(entry("2024-01-01", "skip: false").skip, None);
+        assert_eq!(entry("2024-01-01", "skip: true").skip, Some(vec![]));
+        assert_eq!(
+            entry("2024-01-01", "skip: sick").skip,
+            Some(vec!["sick".to_owned()])
+        );
+        assert_eq!(
+            entry("2024-01-01", "skip: [sick, travel]").skip,
+            Some(vec!["sick".to_owned(), "travel".to_owned()])
+        );
+    }
+
+    #[test]
+    fn skips() {
+        let config: Config = toml::from_str(
+            r#"
+            [habits.workout]
+            aliases = ["gym"]
+
+            [[skip]]
+            from = 2024-01-10
+            to = "2024-01-12"
+            habits = ["gym"]
+            reason = "injury"
+
+            [[skip]]
+            from = "2024-01-20"
+            "#,
+        )
+        .unwrap();
+        let fm = [
+            entry("2024-01-01", "skip: sick"),
+            entry("2024-01-02", "habits:\n  read: skip\n  workout: true"),
+            entry("2024-01-03", "habits:\n  read: true"),
+        ];
+        let skips = Skips::new(&fm, &config);
+        let skipped = |habit, day: &str| skips.skipped(habit, date(day));
+
+        assert!(skipped("read", "2024-01-01") && skipped("workout", "2024-01-01"));
+        assert!(skipped("read", "2024-01-02") && !skipped("workout", "2024-01-02"));
+        assert!(!skipped("read", "2024-01-03"));
+        assert!(!skipped("workout", "2024-01-09"));
+        assert!(skipped("workout", "2024-01-10") && skipped("workout", "2024-01-12"));
+        assert!(!skipped("workout", "2024-01-13") && !skipped("read", "2024-01-11"));
+        assert!(skipped("read", "2024-01-20") && !skipped("read", "2024-01-21"));
+        assert_eq!(
+            config.skip[0].describe(),
+            "2024-01-10 to 2024-01-12 for gym (injury)"
+        );
+    }
+
+    #[test]
+    fn bad_dates() {
+        let bad = toml::from_str::<Config>("[[skip]]\nfrom = \"10 jan\"").unwrap_err();
+        assert!(
+            bad.to_string()
+                .contains("`10 jan` is not a YYYY-MM-DD date"),
+            "{bad}"
+        );
+    }
+}
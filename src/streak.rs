@@ -0,0 +1,265 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{self, Write},
+};
+
+use chrono::NaiveDate;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::{by_day, config::Config, output::Report, schedule::HabitDays, value::Polarity, Fm};
+
+// Whether a day without a journal entry ends a streak or is passed over.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum MissingDays {
+    #[default]
+    Break,
+    Ignore,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Run {
+    pub(crate) start: NaiveDate,
+    pub(crate) end: NaiveDate,
+    pub(crate) days: usize,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct Streak {
+    pub(crate) current: usize,
+    pub(crate) longest: Option<Run>,
+    // The last day an avoided habit was done.
+    pub(crate) last_slip: Option<NaiveDate>,
+}
+
+// Walks the due days with an entry for one habit; a day with entries in
+// several journals counts as done if any of them has it. Unscheduled and
+// skipped days neither extend nor break a streak. A streak still counts as
+// current if today has not been filled in yet but the previous due day was.
+// For a habit to avoid, a streak is a run of days it was not done.
+pub(crate) fn streak(
+    fm: &[Fm],
+    habit: &str,
+    due: &HabitDays,
+    missing: MissingDays,
+    today: NaiveDate,
+) -> Streak {
+    let days: BTreeMap<NaiveDate, bool> = by_day(fm.iter().filter(|f| due.due(f.date)))
+        .into_iter()
+        .map(|(date, habits)| (date, habits.get(habit).is_some_and(|v| v.done())))
+        .collect();
+
+    let mut run: Option<Run> = None;
+    let mut longest: Option<Run> = None;
+    let last_entry = days.keys().next_back().copied();
+    let last_slip = days
+        .iter()
+        .rev()
+        .find(|(_, &done)| due.avoid() && done)
+        .map(|(&date, _)| date);
+
+    for (date, done) in days {
+        if !due.kept(done) {
+            run = None;
+            continue;
+        }
+        let r = match run {
+            Some(r) if missing == MissingDays::Ignore || due.due_after(r.end) == date => Run {
+                end: date,
+                days: r.days + 1,
+                ..r
+            },
+            _ => Run {
+                start: date,
+                end: date,
+                days: 1,
+            },
+        };
+        if longest.is_none_or(|l| r.days >= l.days) {
+            longest = Some(r);
+        }
+        run = Some(r);
+    }
+
+    let current = run.filter(|r| match missing {
+        MissingDays::Break => r.end >= due.due_before(today),
+        MissingDays::Ignore => Some(r.end) == last_entry,
+    });
+    Streak {
+        current: current.map_or(0, |r| r.days),
+        longest,
+        last_slip,
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct StreakRow {
+    habit: String,
+    current: usize,
+    longest: usize,
+    longest_start: Option<NaiveDate>,
+    longest_end: Option<NaiveDate>,
+    polarity: Polarity,
+    last_slip: Option<NaiveDate>,
+    // Days since `last_slip`, for habits to avoid.
+    since_last: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(transparent)]
+pub(crate) struct StreakReport(Vec<StreakRow>);
+
+pub(crate) fn streaks(
+    fm: &[Fm],
+    names: &BTreeSet<String>,
+    config: &Config,
+    missing: MissingDays,
+    today: NaiveDate,
+) -> StreakReport {
+    StreakReport(
+        names
+            .iter()
+            .map(|habit| {
+                let due = config.days(habit);
+                let streak = streak(fm, habit, &due, missing, today);
+                StreakRow {
+                    habit: habit.clone(),
+                    current: streak.current,
+                    longest: streak.longest.map_or(0, |l| l.days),
+                    longest_start: streak.longest.map(|l| l.start),
+                    longest_end: streak.longest.map(|l| l.end),
+                    polarity: due.polarity,
+                    last_slip: streak.last_slip,
+                    since_last: streak.last_slip.map(|date| (today - date).num_days()),
+                }
+            })
+            .collect(),
+    )
+}
+
+impl Report for StreakReport {
+    type Row = StreakRow;
+
+    fn rows(&self) -> Vec<StreakRow> {
+        self.0.clone()
+    }
+
+    fn table(&self, out: &mut dyn Write) -> io::Result<()> {
+        let width = self.0.iter().map(|row| row.habit.len()).max().unwrap_or(0);
+        for row in &self.0 {
+            let mut longest = format!("{:>4}", row.longest);
+            if let (Some(start), Some(end)) = (row.longest_start, row.longest_end) {
+                longest.push_str(&format!(" ({start} to {end})"));
+            }
+            let since = match (row.polarity, row.since_last) {
+                (Polarity::Build, _) => String::new(),
+                (Polarity::Avoid, Some(1)) => "  1 day since last".to_owned(),
+                (Polarity::Avoid, Some(days)) => format!("  {days} days since last"),
+                (Polarity::Avoid, None) => "  never done".to_owned(),
+            };
+            writeln!(
+                out,
+                "{:width$}  current {:>4}  longest {longest}{since}",
+                row.habit, row.current
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{schedule::Schedule, skip::Skips};
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    // One entry a day from 2024-01-01, `read` done where `done` has a `y`.
+    fn entries(done: &str) -> Vec<Fm> {
+        done.chars()
+            .zip(date("2024-01-01").iter_days())
+            .filter(|(c, _)| *c != ' ')
+            .map(|(c, date)| {
+                let src = format!("title: t\ndate: {date}\nhabits:\n  read: {}\n", c == 'y');
+                serde_yaml::from_str(&src).unwrap()
+            })
+            .collect()
+    }
+
+    fn run(done: &str, schedule: &Schedule, polarity: Polarity, missing: MissingDays) -> Streak {
+        let skips = Skips::default();
+        let due = HabitDays {
+            habit: "read",
+            schedule,
+            skips: &skips,
+            polarity,
+        };
+        let today = date("2024-01-01") + chrono::Duration::days(done.len() as i64);
+        streak(&entries(done), "read", &due, missing, today)
+    }
+
+    #[test]
+    fn current_and_longest() {
+        let s = run(
+            "yyynyy",
+            &Schedule::Daily,
+            Polarity::Build,
+            MissingDays::Break,
+        );
+        assert_eq!(s.current, 2);
+        let longest = s.longest.unwrap();
+        assert_eq!((longest.start, longest.days), (date("2024-01-01"), 3));
+    }
+
+    #[test]
+    fn missing_days() {
+        let s = run(
+            "yy yy",
+            &Schedule::Daily,
+            Polarity::Build,
+            MissingDays::Break,
+        );
+        assert_eq!((s.current, s.longest.unwrap().days), (2, 2));
+        let s = run(
+            "yy yy",
+            &Schedule::Daily,
+            Polarity::Build,
+            MissingDays::Ignore,
+        );
+        assert_eq!((s.current, s.longest.unwrap().days), (4, 4));
+    }
+
+    #[test]
+    fn broken() {
+        let s = run(
+            "yyyn",
+            &Schedule::Daily,
+            Polarity::Build,
+            MissingDays::Break,
+        );
+        assert_eq!((s.current, s.longest.unwrap().days), (0, 3));
+    }
+
+    #[test]
+    fn unscheduled_days_are_passed_over() {
+        // 2024-01-01 is a Monday.
+        let schedule = Schedule::On(vec![chrono::Weekday::Mon, chrono::Weekday::Wed]);
+        let s = run("ynyn", &schedule, Polarity::Build, MissingDays::Break);
+        assert_eq!(s.current, 2);
+    }
+
+    #[test]
+    fn avoid() {
+        let s = run(
+            "nnynnn",
+            &Schedule::Daily,
+            Polarity::Avoid,
+            MissingDays::Break,
+        );
+        assert_eq!((s.current, s.longest.unwrap().days), (3, 3));
+        assert_eq!(s.last_slip, Some(date("2024-01-03")));
+    }
+}
@@ -0,0 +1,321 @@
+use std::fmt;
+
+use serde::{
+    de::{self, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+// What a habit was recorded as on one day: done or not, an amount such as
+// pages read, a duration, kept in minutes, or `skip` for a day that should
+// not count either way.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum HabitValue {
+    Bool(bool),
+    Number(f64),
+    Duration(f64),
+    Skip,
+}
+
+impl HabitValue {
+    // Any amount above zero counts as doing the habit.
+    pub(crate) fn done(self) -> bool {
+        match self {
+            Self::Bool(done) => done,
+            Self::Number(n) | Self::Duration(n) => n > 0.0,
+            Self::Skip => false,
+        }
+    }
+
+    pub(crate) const fn amount(self) -> Option<f64> {
+        match self {
+            Self::Bool(_) | Self::Skip => None,
+            Self::Number(n) | Self::Duration(n) => Some(n),
+        }
+    }
+
+    // Two keys for the same habit in one entry: amounts add up, otherwise
+    // either being done is enough.
+    pub(crate) fn merge(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::Duration(a), Self::Duration(b)) => Self::Duration(a + b),
+            (Self::Number(a) | Self::Duration(a), Self::Number(b) | Self::Duration(b)) => {
+                Self::Number(a + b)
+            }
+            (a, b) if b.done() && !a.done() => b,
+            (Self::Bool(false), Self::Skip) => Self::Skip,
+            (a, _) => a,
+        }
+    }
+
+    // Accepts `true`/`false`, `yes`/`no`, `skip`, a number or a duration.
+    pub(crate) fn parse(s: &str) -> Result<Self, String> {
+        let s = s.trim();
+        match s.to_lowercase().as_str() {
+            "true" | "y" | "yes" => return Ok(Self::Bool(true)),
+            "false" | "n" | "no" => return Ok(Self::Bool(false)),
+            "skip" | "s" => return Ok(Self::Skip),
+            _ => {}
+        }
+        let invalid = || format!("`{s}` is not a boolean, number, duration like `45m` or `skip`");
+        match s.parse::<f64>() {
+            Ok(n) if n.is_finite() => Ok(Self::Number(n)),
+            Ok(_) => Err(invalid()),
+            Err(_) => parse_duration(s).map(Self::Duration).ok_or_else(invalid),
+        }
+    }
+
+    // Whether the value is written as a string, which TOML needs quoted.
+    pub(crate) const fn is_text(self) -> bool {
+        matches!(self, Self::Duration(_) | Self::Skip)
+    }
+}
+
+impl fmt::Display for HabitValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bool(done) => write!(f, "{done}"),
+            Self::Number(n) => write!(f, "{n}"),
+            Self::Duration(minutes) => write!(f, "{}", format_duration(*minutes)),
+            Self::Skip => write!(f, "skip"),
+        }
+    }
+}
+
+impl Serialize for HabitValue {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Bool(done) => s.serialize_bool(*done),
+            Self::Number(n) => s.serialize_f64(*n),
+            Self::Duration(_) | Self::Skip => s.collect_str(self),
+        }
+    }
+}
+
+// Whether doing a habit is the aim, or something to stay away from, in which
+// case a day it was done on is a slip and every other day is kept clean.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Polarity {
+    #[default]
+    #[serde(alias = "positive")]
+    Build,
+    #[serde(alias = "negative")]
+    Avoid,
+}
+
+impl Polarity {
+    // Whether a day went the way it should have.
+    pub(crate) const fn kept(self, done: bool) -> bool {
+        match self {
+            Self::Build => done,
+            Self::Avoid => !done,
+        }
+    }
+}
+
+struct ValueVisitor;
+
+impl Visitor<'_> for ValueVisitor {
+    type Value = HabitValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a boolean, number, duration like `45m` or `skip`")
+    }
+
+    fn visit_bool<E: de::Error>(self, done: bool) -> Result<HabitValue, E> {
+        Ok(HabitValue::Bool(done))
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn visit_i64<E: de::Error>(self, n: i64) -> Result<HabitValue, E> {
+        Ok(HabitValue::Number(n as f64))
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn visit_u64<E: de::Error>(self, n: u64) -> Result<HabitValue, E> {
+        Ok(HabitValue::Number(n as f64))
+    }
+
+    fn visit_f64<E: de::Error>(self, n: f64) -> Result<HabitValue, E> {
+        if n.is_finite() {
+            Ok(HabitValue::Number(n))
+        } else {
+            Err(E::custom(format!(
+                "`{n}` is not a boolean, number, duration like `45m` or `skip`"
+            )))
+        }
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<HabitValue, E> {
+        if s.trim().eq_ignore_ascii_case("skip") {
+            return Ok(HabitValue::Skip);
+        }
+        parse_duration(s).map(HabitValue::Duration).ok_or_else(|| {
+            E::custom(format!(
+                "`{s}` is not a boolean, number, duration like `45m` or `skip`"
+            ))
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for HabitValue {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        d.deserialize_any(ValueVisitor)
+    }
+}
+
+// Minutes in `1h30m`, `45m`, `90s`, `1.5h` or `1:30`.
+pub(crate) fn parse_duration(s: &str) -> Option<f64> {
+    let s = s.trim();
+    if let Some((hours, minutes)) = s.split_once(':') {
+        let hours: u32 = hours.parse().ok()?;
+        let minutes: u32 = minutes.parse().ok().filter(|m| *m < 60)?;
+        let total = hours.checked_mul(60)?.checked_add(minutes)?;
+        return Some(f64::from(total));
+    }
+    let mut minutes = 0.0;
+    let mut rest = s;
+    while !rest.is_empty() {
+        let split = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .filter(|&n| n > 0)?;
+        let n: f64 = rest[..split].parse().ok()?;
+        let unit_end = rest[split..]
+            .find(|c: char| c.is_ascii_digit() || c == ' ')
+            .map_or(rest.len(), |n| n + split);
+        minutes += n * match &rest[split..unit_end] {
+            "h" | "hr" | "hrs" | "hour" | "hours" => 60.0,
+            "m" | "min" | "mins" | "minute" | "minutes" => 1.0,
+            "s" | "sec" | "secs" | "second" | "seconds" => 1.0 / 60.0,
+            _ => return None,
+        };
+        rest = rest[unit_end..].trim_start();
+    }
+    Some(minutes).filter(|m| !s.is_empty() && m.is_finite())
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub(crate) fn format_duration(minutes: f64) -> String {
+    let seconds = (minutes * 60.0).round().max(0.0) as u64;
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    let mut out = String::new();
+    if hours > 0 {
+        out.push_str(&format!("{hours}h"));
+    }
+    if minutes > 0 || (hours == 0 && seconds == 0) {
+        out.push_str(&format!("{minutes}m"));
+    }
+    if seconds > 0 {
+        out.push_str(&format!("{seconds}s"));
+    }
+    out
+}
+
+// Sum, mean and extremes of the amounts recorded for one habit.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Stats {
+    pub(crate) n: usize,
+    pub(crate) sum: f64,
+    pub(crate) min: f64,
+    pub(crate) max: f64,
+    // Whether every amount was a duration, so totals read as one.
+    pub(crate) duration: bool,
+}
+
+impl Stats {
+    pub(crate) fn add(&mut self, value: HabitValue) {
+        let Some(amount) = value.amount() else {
+            return;
+        };
+        let duration = matches!(value, HabitValue::Duration(_));
+        if self.n == 0 {
+            *self = Self {
+                n: 0,
+                sum: 0.0,
+                min: amount,
+                max: amount,
+                duration,
+            };
+        }
+        self.n += 1;
+        self.sum += amount;
+        self.min = self.min.min(amount);
+        self.max = self.max.max(amount);
+        self.duration &= duration;
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    pub(crate) fn mean(&self) -> f64 {
+        if self.n == 0 {
+            0.0
+        } else {
+            self.sum / self.n as f64
+        }
+    }
+
+    // An amount with the habit's unit, or as a duration.
+    pub(crate) fn format(&self, amount: f64, unit: Option<&str>) -> String {
+        if self.duration {
+            return format_duration(amount);
+        }
+        let amount = format!("{:.2}", amount);
+        let amount = amount.trim_end_matches('0').trim_end_matches('.');
+        match unit {
+            Some(unit) => format!("{amount} {unit}"),
+            None => amount.to_owned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("45m"), Some(45.0));
+        assert_eq!(parse_duration("1h30m"), Some(90.0));
+        assert_eq!(parse_duration("1hr 30mins"), Some(90.0));
+        assert_eq!(parse_duration("1.5h"), Some(90.0));
+        assert_eq!(parse_duration("90s"), Some(1.5));
+        assert_eq!(parse_duration("1:30"), Some(90.0));
+    }
+
+    #[test]
+    fn not_durations() {
+        for s in ["", "45", "h", "1:75", "99999999:00", "5 parsecs", "maybe"] {
+            assert_eq!(parse_duration(s), None, "{s}");
+        }
+    }
+
+    #[test]
+    fn parse_values() {
+        assert_eq!(HabitValue::parse("yes"), Ok(HabitValue::Bool(true)));
+        assert_eq!(HabitValue::parse("N"), Ok(HabitValue::Bool(false)));
+        assert_eq!(HabitValue::parse("skip"), Ok(HabitValue::Skip));
+        assert_eq!(HabitValue::parse("12"), Ok(HabitValue::Number(12.0)));
+        assert_eq!(HabitValue::parse("20m"), Ok(HabitValue::Duration(20.0)));
+        for s in ["maybe", "nan", "inf", "-inf", "1e400"] {
+            assert!(HabitValue::parse(s).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn deserialize_values() {
+        let values: Vec<HabitValue> = serde_yaml::from_str("[true, 3, 2.5, 45m, Skip]").unwrap();
+        assert_eq!(
+            values,
+            [
+                HabitValue::Bool(true),
+                HabitValue::Number(3.0),
+                HabitValue::Number(2.5),
+                HabitValue::Duration(45.0),
+                HabitValue::Skip
+            ]
+        );
+        let e = serde_yaml::from_str::<HabitValue>("maybe").unwrap_err();
+        assert!(e.to_string().contains("`maybe` is not a boolean"), "{e}");
+        assert!(serde_yaml::from_str::<HabitValue>(".nan").is_err());
+        assert!(serde_yaml::from_str::<HabitValue>("99999999:00").is_err());
+    }
+}
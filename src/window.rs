@@ -0,0 +1,115 @@
+use std::{
+    collections::BTreeSet,
+    io::{self, Write},
+};
+
+use chrono::{Duration, NaiveDate};
+use serde::Serialize;
+
+use crate::{
+    config::Config,
+    count,
+    output::Report,
+    percent,
+    range::{back, DateRange},
+    Fm,
+};
+
+const REPORT_WINDOWS: [i64; 4] = [7, 30, 90, 365];
+
+// The `days` calendar days ending on, and including, `end`.
+pub(crate) fn rolling(days: i64, end: NaiveDate) -> DateRange {
+    DateRange::new(back(end, days - 1), end)
+}
+
+// The most recent `n` entries of a date-sorted list, however many days apart
+// they are.
+pub(crate) fn last_entries(mut fm: Vec<Fm>, n: usize) -> Vec<Fm> {
+    let skip = fm.len().saturating_sub(n);
+    fm.split_off(skip)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct WindowRow {
+    habit: String,
+    days: i64,
+    count: usize,
+    bonus: usize,
+    skipped: usize,
+    rate: f64,
+}
+
+// Counts and completion rates for every habit over each report window, shown
+// side by side. Rates are out of the days each habit is due: scheduled and
+// not skipped.
+#[derive(Debug, Serialize)]
+pub(crate) struct WindowsReport {
+    end: NaiveDate,
+    habits: Vec<WindowRow>,
+}
+
+pub(crate) fn windows(
+    fm: &[Fm],
+    names: &BTreeSet<String>,
+    end: NaiveDate,
+    config: &Config,
+) -> WindowsReport {
+    let counts: Vec<_> = REPORT_WINDOWS
+        .iter()
+        .map(|&days| {
+            let range = rolling(days, end);
+            (
+                days,
+                count(fm.iter().filter(|f| range.contains(f.date)), names, config),
+            )
+        })
+        .collect();
+    let habits = names
+        .iter()
+        .flat_map(|habit| {
+            let due = config.days(habit);
+            counts.iter().map(move |(days, count)| {
+                let start = end - Duration::days(days - 1);
+                let kept = count.kept(&due);
+                WindowRow {
+                    habit: habit.clone(),
+                    days: *days,
+                    count: kept,
+                    bonus: count.bonus(&due),
+                    skipped: due.skipped_days(start, end),
+                    rate: percent(kept, due.due_days(start, end)),
+                }
+            })
+        })
+        .collect();
+    WindowsReport { end, habits }
+}
+
+impl Report for WindowsReport {
+    type Row = WindowRow;
+
+    fn rows(&self) -> Vec<WindowRow> {
+        self.habits.clone()
+    }
+
+    fn table(&self, out: &mut dyn Write) -> io::Result<()> {
+        let width = self
+            .habits
+            .iter()
+            .map(|row| row.habit.len())
+            .max()
+            .unwrap_or(0);
+        write!(out, "{:width$}", "")?;
+        for days in REPORT_WINDOWS {
+            write!(out, "{:>11}", format!("{days}d"))?;
+        }
+        for (n, row) in self.habits.iter().enumerate() {
+            if n % REPORT_WINDOWS.len() == 0 {
+                write!(out, "\n{:width$}", row.habit)?;
+            }
+            write!(out, "{:>6} {:>3.0}%", row.count, row.rate)?;
+        }
+        writeln!(out)?;
+        Ok(())
+    }
+}